@@ -3,7 +3,7 @@ use clap_verbosity_flag::ErrorLevel;
 use serde::de::{Deserialize, Deserializer};
 use serde::ser::{Serialize, Serializer};
 
-use crate::crawl::SitePolicy;
+use crate::crawl::{AuthMode, LinkSource, SitePolicy, TlsVersion, DEFAULT_TRACKING_PARAMS};
 use crate::extract::FilterMode;
 
 use super::helpers;
@@ -17,6 +17,18 @@ pub struct Cli {
     /// Limit the depth of crawling URLs.
     #[arg(short, long, default_value_t = 1)]
     pub depth: usize,
+    /// Stop crawling once words have been extracted from this many pages; 0 for no limit. The
+    /// crawl ends as when interrupted, so it can be resumed with a fresh budget.
+    #[arg(long, default_value_t = 0)]
+    pub max_pages: usize,
+    /// Stop crawling once this many bytes of documents have been read; 0 for no limit. The
+    /// crawl ends as when interrupted, so it can be resumed with a fresh budget.
+    #[arg(long, default_value_t = 0)]
+    pub max_bytes: u64,
+    /// Stop crawling after this many seconds, letting requests in flight complete; 0 for no
+    /// limit. The crawl ends as when interrupted, so it can be resumed with a fresh budget.
+    #[arg(long, default_value_t = 0)]
+    pub max_duration: u64,
     /// Only save words greater than or equal to this value.
     #[arg(short, long, default_value_t = 3)]
     pub min_word_length: usize,
@@ -38,6 +50,20 @@ pub struct Cli {
         value_delimiter = ',',
     )]
     pub filters: Vec<FilterArg>,
+    /// Sources of links to follow; multiple can be specified (comma separated);
+    /// <script src> follows --include-js, and <link> follows --include-css/--include-js.
+    #[arg(
+        long,
+        default_value = "anchor,frame,form,refresh",
+        value_enum,
+        num_args = 1..,
+        value_delimiter = ',',
+    )]
+    pub follow_links: Vec<LinkArg>,
+    /// Relations of <link> elements to follow (e.g. next, prev, alternate), besides stylesheets
+    /// and scripts; multiple can be specified (comma separated); an empty value follows none.
+    #[arg(long, default_value = "next,prev", num_args = 1.., value_delimiter = ',')]
+    pub follow_rel: Vec<String>,
     /// Site policy for discovered URLs.
     #[arg(short, long, default_value = "same", value_enum)]
     pub site_policy: SitePolicyArg,
@@ -47,57 +73,272 @@ pub struct Cli {
     /// HTTP headers to send with requests; can be specified multiple times (key=value).
     #[arg(long, value_parser = helpers::headers_parser())]
     pub header: Option<Vec<(String, String)>>,
+    /// Only crawl URLs whose path and query match this regex; can be specified multiple times.
+    #[arg(long, value_parser = helpers::regex_parser())]
+    pub include: Vec<String>,
+    /// Skip URLs whose path and query match this regex; can be specified multiple times.
+    #[arg(long, value_parser = helpers::regex_parser())]
+    pub exclude: Vec<String>,
+    /// Only crawl URLs whose path and query match this glob (e.g. '/docs/**');
+    /// can be specified multiple times.
+    #[arg(long)]
+    pub include_glob: Vec<String>,
+    /// Skip URLs whose path and query match this glob (e.g. '**.pdf');
+    /// can be specified multiple times.
+    #[arg(long)]
+    pub exclude_glob: Vec<String>,
+    /// Proxy to route all requests through (http, https, socks5 or socks5h URL, e.g.
+    /// socks5h://127.0.0.1:1080); credentials may be given within the URL or via --proxy-auth.
+    #[arg(long, value_parser = helpers::proxy_parser())]
+    pub proxy: Option<String>,
+    /// Proxy to route http:// requests through, taking precedence over --proxy.
+    #[arg(long, value_parser = helpers::proxy_parser())]
+    pub http_proxy: Option<String>,
+    /// Proxy to route https:// requests through, taking precedence over --proxy.
+    #[arg(long, value_parser = helpers::proxy_parser())]
+    pub https_proxy: Option<String>,
+    /// Credentials for proxies (user:password); never written to the state file, so must be
+    /// given again when resuming.
+    #[arg(long, value_parser = helpers::proxy_auth_parser())]
+    pub proxy_auth: Option<(String, String)>,
+    /// Hosts, domains (e.g. .example.com), IPs or CIDR ranges to connect to directly,
+    /// bypassing proxies; multiple can be specified (comma separated).
+    #[arg(long, num_args = 1.., value_delimiter = ',')]
+    pub no_proxy: Vec<String>,
+    /// Import cookies from a Netscape cookies.txt file (e.g. exported from a browser or curl);
+    /// cookies are kept across requests, and saved to the state file with --state-cookies.
+    #[arg(long)]
+    pub cookies: Option<String>,
+    /// Credentials (user:password) for HTTP Basic or Digest authentication; only presented to
+    /// the target's origin (scheme, host and port), and never written to the state file, so
+    /// must be given again when resuming.
+    #[arg(long, value_parser = helpers::credentials_parser())]
+    pub auth: Option<(String, String)>,
+    /// Schemes of authentication challenges to answer with --auth; basic sends credentials
+    /// with every request, without awaiting a challenge.
+    #[arg(long, default_value = "any", value_enum, requires = "auth")]
+    pub auth_type: AuthTypeArg,
+    /// Token for HTTP Bearer authentication; only presented to the target's origin, and never
+    /// written to the state file, so must be given again when resuming.
+    #[arg(long, conflicts_with = "auth", value_parser = helpers::str_not_whitespace_parser())]
+    pub bearer_token: Option<String>,
+    /// Log in before crawling by submitting the form found at this URL (absolute, or relative
+    /// to the target); only do this when authorized, and --exclude logout links. Login options
+    /// are never written to the state file, so must be given again when resuming.
+    #[arg(long)]
+    pub login_url: Option<String>,
+    /// Login form field to fill in (name=value), e.g. username or password; can be specified
+    /// multiple times. Other fields of the form, such as CSRF tokens, are submitted as found.
+    #[arg(long, requires = "login_url", value_parser = helpers::login_field_parser())]
+    pub login_field: Vec<(String, String)>,
+    /// CSS selector of the login form [default: the first form with a password field].
+    #[arg(long, requires = "login_url", value_parser = helpers::selector_parser())]
+    pub login_form: Option<String>,
+    /// Regex that the response to a successful login must match.
+    #[arg(long, requires = "login_url", value_parser = helpers::regex_parser())]
+    pub login_success: Option<String>,
+    /// Status codes (e.g. 200) or ranges (e.g. 200-299) of the response to a successful login,
+    /// after redirects; multiple can be specified (comma separated) [default: 200-299].
+    #[arg(
+        long,
+        requires = "login_url",
+        value_parser = helpers::status_range_parser(),
+        num_args = 1..,
+        value_delimiter = ',',
+    )]
+    pub login_success_status: Vec<(u16, u16)>,
+    /// Regex marking pages served once logged out (e.g. 'Please sign in'); when a crawled page
+    /// matches, the login is repeated and the page fetched again.
+    #[arg(long, requires = "login_url", value_parser = helpers::regex_parser())]
+    pub logged_out: Option<String>,
+    /// Additional root CA certificates (PEM) to trust; can be specified multiple times.
+    #[arg(long)]
+    pub ca_cert: Vec<String>,
+    /// Client certificate for mutual TLS; either PEM (with --client-key, unless the key is in
+    /// the same file) or PKCS#12 (.p12/.pfx).
+    #[arg(long)]
+    pub client_cert: Option<String>,
+    /// Private key (PEM) for --client-cert.
+    #[arg(long, requires = "client_cert")]
+    pub client_key: Option<String>,
+    /// Password for a PKCS#12 --client-cert; never written to the state file, so must be given
+    /// again when resuming.
+    #[arg(long, requires = "client_cert")]
+    pub client_cert_password: Option<String>,
+    /// Minimum TLS version to negotiate.
+    #[arg(long, value_enum)]
+    pub min_tls_version: Option<TlsVersionArg>,
+    /// Disable TLS certificate and hostname verification (dangerous; only for trusted networks).
+    #[arg(long, default_value_t = false)]
+    pub insecure: bool,
+    /// Ignore robots.txt rules while crawling (only do this when authorized).
+    #[arg(long, default_value_t = false)]
+    pub ignore_robots: bool,
+    /// Seed crawling with URLs discovered from sitemaps (robots.txt and /sitemap.xml).
+    #[arg(long, default_value_t = false)]
+    pub sitemaps: bool,
     /// Number of requests to make per second.
     #[arg(short, long, default_value_t = 10)]
     pub req_per_sec: u64,
     /// Limit the number of concurrent requests to this value.
     #[arg(short = 'l', long, default_value_t = 10)]
     pub limit_concurrent: usize,
+    /// Number of requests to make per second to any single host [default: --req-per-sec].
+    #[arg(long)]
+    pub host_req_per_sec: Option<u64>,
+    /// Limit the number of concurrent requests to any single host [default: --limit-concurrent].
+    #[arg(long)]
+    pub host_limit_concurrent: Option<usize>,
+    /// Maximum number of retries for throttled (429) or failed (5xx) requests.
+    #[arg(long, default_value_t = 3)]
+    pub max_retries: usize,
+    /// Initial delay in milliseconds before retrying a request; doubled on each retry.
+    #[arg(long, default_value_t = 500)]
+    pub retry_backoff: u64,
+    /// Maximum delay in seconds between retries, including delays requested via Retry-After.
+    #[arg(long, default_value_t = 60)]
+    pub max_retry_delay: u64,
+    /// Timeout in seconds for connecting to a host.
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u64).range(1..))]
+    pub connect_timeout: u64,
+    /// Timeout in seconds for a request, from connecting until the whole body is read.
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+    pub timeout: u64,
+    /// Maximum number of redirects to follow per request; redirects leaving the site policy
+    /// are never followed. With 0, redirect targets are queued like any other discovered URL.
+    #[arg(long, default_value_t = 10)]
+    pub max_redirects: usize,
+    /// Status codes (e.g. 200) or ranges (e.g. 200-299) of responses to extract words from;
+    /// multiple can be specified (comma separated).
+    #[arg(
+        long,
+        default_value = "200-299",
+        value_parser = helpers::status_range_parser(),
+        num_args = 1..,
+        value_delimiter = ',',
+    )]
+    pub accept_status: Vec<(u16, u16)>,
+    /// Maximum size in bytes of a document (web or local) to download; 0 for no limit.
+    #[arg(long, default_value_t = 0)]
+    pub max_doc_size: u64,
+    /// Truncate documents exceeding the maximum size, rather than skipping them.
+    #[arg(long, default_value_t = false)]
+    pub truncate_oversized: bool,
+    /// Query parameters to strip from discovered URLs; a trailing '*' matches a prefix;
+    /// multiple can be specified (comma separated); an empty value strips nothing.
+    #[arg(
+        long,
+        default_values_t = DEFAULT_TRACKING_PARAMS.map(String::from),
+        num_args = 1..,
+        value_delimiter = ',',
+    )]
+    pub strip_params: Vec<String>,
+    /// Keep the original order of query parameters in discovered URLs, rather than sorting them.
+    #[arg(long, default_value_t = false)]
+    pub keep_query_order: bool,
+    /// Remove trailing slashes from the paths of discovered URLs.
+    #[arg(long, default_value_t = false)]
+    pub fold_trailing_slash: bool,
     /// File to write dictionary to (will be overwritten if it already exists).
     #[arg(short, long, default_value = "wdict.txt", value_parser = helpers::str_not_whitespace_parser())]
     pub output: String,
+    /// With --resume or --resume-strict, visit previously visited URLs again, only downloading
+    /// and extracting words from pages changed since (via ETag/Last-Modified); the crawl
+    /// restarts from depth 0, so links newly found on changed pages are followed as well.
+    #[arg(long, default_value_t = false)]
+    pub refresh: bool,
+    /// Write all fetched responses to a WARC file (gzipped if it ends in .gz), from which
+    /// dictionaries can later be regenerated via --warc-input; appended to when resuming.
+    #[arg(long, value_parser = helpers::str_not_whitespace_parser())]
+    pub warc_output: Option<String>,
     /// Append extracted words to an existing dictionary.
     #[arg(long, default_value_t = false)]
     pub append: bool,
     /// Skip writing words to an output file (i.e. save your disk while benchmarking).
     #[arg(long, default_value_t = false)]
     pub no_write: bool,
-    /// Write crawl state to a file.
+    /// Write crawl state to a file, readable only by its owner.
     #[arg(long, default_value_t = false)]
     pub output_state: bool,
     /// File to write state, json formatted (will be overwritten if it already exists).
     #[arg(long, default_value = "state-wdict.json", value_parser = helpers::str_not_whitespace_parser())]
     pub state_file: String,
+    /// Save cookies to the state file, to be restored when resuming. Session and login cookies
+    /// are credentials, so the state file must then be kept private; without this, cookies are
+    /// left out of the state file.
+    #[arg(long, default_value_t = false)]
+    pub state_cookies: bool,
     /// Control verbosity of logging.
     #[command(flatten)]
     pub verbose: clap_verbosity_flag::Verbosity<ErrorLevel>,
 }
 
 #[derive(Args, Debug, Clone)]
-#[group(required = true, multiple = false)]
+#[group(required = true, multiple = true)]
 pub struct Target {
-    /// URL to start crawling from.
+    /// URL to start crawling from; can be repeated, and combined with --path, --seed-file and
+    /// --theme; site policy is evaluated against each seed.
     #[arg(short, long, value_parser = helpers::str_not_whitespace_parser())]
-    pub url: Option<String>,
+    pub url: Vec<String>,
 
     /// Pre-canned theme URLs to start crawling from (for fun).
     #[arg(long, value_enum)]
     pub theme: Option<Theme>,
 
-    /// Local file path to start crawling from.
+    /// Local file path to start crawling from; can be repeated.
     #[arg(short, long, value_parser = helpers::str_not_whitespace_parser())]
-    pub path: Option<String>,
+    pub path: Vec<String>,
+
+    /// File of URLs, local paths and host names (crawled over https) to start crawling from, one
+    /// per line; blank lines and lines starting with '#' are ignored.
+    #[arg(long, value_parser = helpers::str_not_whitespace_parser())]
+    pub seed_file: Option<String>,
+
+    /// Read from stdin either URLs to start crawling from, one per line (as with --seed-file), or
+    /// a raw document to extract words from, without crawling.
+    #[arg(long, value_enum)]
+    pub stdin: Option<StdinArg>,
+
+    /// WARC file to crawl in place of the web (e.g. written by --warc-output), starting from
+    /// the URL it was written from; pages not in the archive are skipped.
+    #[arg(
+        long,
+        value_parser = helpers::str_not_whitespace_parser(),
+        conflicts_with_all = ["url", "theme", "path", "seed_file", "stdin"],
+    )]
+    pub warc_input: Option<String>,
+
+    /// HAR file to extract words from (e.g. exported by browser developer tools or a proxy),
+    /// walking its entries rather than crawling; site policy is evaluated against the first entry.
+    #[arg(
+        long,
+        value_parser = helpers::str_not_whitespace_parser(),
+        conflicts_with_all = ["url", "theme", "path", "seed_file", "stdin", "warc_input"],
+    )]
+    pub har_input: Option<String>,
 
     /// Resume crawling from a previous run;
     /// state file must exist; existence of dictionary is optional;
     /// parameters from state are ignored, instead favoring arguments provided on the command line.
-    #[arg(long, default_value_t = false)]
+    #[arg(
+        long,
+        default_value_t = false,
+        conflicts_with_all = [
+            "url", "theme", "path", "seed_file", "stdin", "warc_input", "har_input",
+        ],
+    )]
     pub resume: bool,
 
     /// Resume crawling from a previous run;
     /// state file must exist; existence of dictionary is optional;
     /// 'strict' enforces that all arguments from the state file are observed.
-    #[arg(long, default_value_t = false)]
+    #[arg(
+        long,
+        default_value_t = false,
+        conflicts_with_all = [
+            "url", "theme", "path", "seed_file", "stdin", "warc_input", "har_input", "resume",
+        ],
+    )]
     pub resume_strict: bool,
 }
 
@@ -342,6 +583,190 @@ impl<'de> Deserialize<'de> for FilterArg {
     }
 }
 
+/// Defines sources of links to follow.
+#[derive(ValueEnum, Copy, Debug, Clone)]
+pub enum LinkArg {
+    /// Follow <a href> and <area href> links.
+    Anchor,
+    /// Follow <iframe src> and <frame src> links.
+    Frame,
+    /// Follow <form action> links of forms submitted via GET.
+    Form,
+    /// Follow <meta http-equiv="refresh"> redirects.
+    Refresh,
+    /// Follow srcset image candidates of <img> and <source> elements.
+    Srcset,
+}
+
+impl LinkArg {
+    /// Get link source from arg; exists just to de-couple lib from clap.
+    pub fn to_mode(&self) -> LinkSource {
+        match self {
+            Self::Anchor => LinkSource::Anchor,
+            Self::Frame => LinkSource::Frame,
+            Self::Form => LinkSource::Form,
+            Self::Refresh => LinkSource::Refresh,
+            Self::Srcset => LinkSource::Srcset,
+        }
+    }
+
+    /// Convert a Vector of LinkArg to a Vector of LinkSource.
+    pub fn to_modes(v: &[Self]) -> Vec<LinkSource> {
+        v.iter().map(|l| l.to_mode()).collect()
+    }
+}
+
+/// Display implementation.
+impl std::fmt::Display for LinkArg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Anchor => write!(f, "anchor"),
+            Self::Frame => write!(f, "frame"),
+            Self::Form => write!(f, "form"),
+            Self::Refresh => write!(f, "refresh"),
+            Self::Srcset => write!(f, "srcset"),
+        }
+    }
+}
+
+/// Serialize implementation.
+impl Serialize for LinkArg {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.to_string().as_str())
+    }
+}
+
+/// Deserialize implementation.
+impl<'de> Deserialize<'de> for LinkArg {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        let ss = s.as_str();
+        match ss {
+            "anchor" => Ok(Self::Anchor),
+            "frame" => Ok(Self::Frame),
+            "form" => Ok(Self::Form),
+            "refresh" => Ok(Self::Refresh),
+            "srcset" => Ok(Self::Srcset),
+            _ => Err(serde::de::Error::custom("Expected a valid link arg")),
+        }
+    }
+}
+
+/// Defines schemes of authentication challenges to answer.
+#[derive(ValueEnum, Copy, Debug, Clone)]
+pub enum AuthTypeArg {
+    /// Answer Basic or Digest challenges, preferring Digest.
+    Any,
+    /// Send Basic credentials with every request.
+    Basic,
+    /// Answer only Digest challenges.
+    Digest,
+}
+
+impl AuthTypeArg {
+    /// Get auth mode from arg; exists just to de-couple lib from clap.
+    pub fn to_mode(&self) -> AuthMode {
+        match self {
+            Self::Any => AuthMode::Any,
+            Self::Basic => AuthMode::Basic,
+            Self::Digest => AuthMode::Digest,
+        }
+    }
+}
+
+/// Display implementation.
+impl std::fmt::Display for AuthTypeArg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Any => write!(f, "any"),
+            Self::Basic => write!(f, "basic"),
+            Self::Digest => write!(f, "digest"),
+        }
+    }
+}
+
+/// Defines interpretations of input read from stdin.
+#[derive(ValueEnum, Copy, Debug, Clone, PartialEq)]
+pub enum StdinArg {
+    /// URLs (or local paths, or host names) to start crawling from, one per line.
+    Urls,
+    /// A raw document (e.g. html or text) to extract words from, without crawling.
+    Document,
+}
+
+/// Display implementation.
+impl std::fmt::Display for StdinArg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Urls => write!(f, "urls"),
+            Self::Document => write!(f, "document"),
+        }
+    }
+}
+
+/// Defines minimum TLS versions.
+#[derive(ValueEnum, Copy, Debug, Clone)]
+pub enum TlsVersionArg {
+    /// TLS 1.2.
+    #[value(name = "1.2")]
+    Tls1_2,
+    /// TLS 1.3.
+    #[value(name = "1.3")]
+    Tls1_3,
+}
+
+impl TlsVersionArg {
+    /// Get TLS version from arg; exists just to de-couple lib from clap.
+    pub fn to_mode(&self) -> TlsVersion {
+        match self {
+            Self::Tls1_2 => TlsVersion::Tls1_2,
+            Self::Tls1_3 => TlsVersion::Tls1_3,
+        }
+    }
+}
+
+/// Display implementation.
+impl std::fmt::Display for TlsVersionArg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tls1_2 => write!(f, "1.2"),
+            Self::Tls1_3 => write!(f, "1.3"),
+        }
+    }
+}
+
+/// Serialize implementation.
+impl Serialize for TlsVersionArg {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.to_string().as_str())
+    }
+}
+
+/// Deserialize implementation.
+impl<'de> Deserialize<'de> for TlsVersionArg {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        let ss = s.as_str();
+        match ss {
+            "1.2" => Ok(Self::Tls1_2),
+            "1.3" => Ok(Self::Tls1_3),
+            _ => Err(serde::de::Error::custom("Expected a valid tls version arg")),
+        }
+    }
+}
+
 /// Defines options for crawling sites.
 #[derive(ValueEnum, Copy, Debug, Clone)]
 pub enum SitePolicyArg {
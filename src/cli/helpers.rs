@@ -1,30 +1,99 @@
+use bytes::Bytes;
 use clap::builder::ValueParser;
 use log::{info, warn};
+use regex::Regex;
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use reqwest::Url;
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
-use std::io::{self, BufRead};
+use std::io::{self, BufRead, Read};
+use std::path::Path;
 use std::str::FromStr;
 
-use crate::collections::{UrlDb, WordDb};
+use crate::collections::{CookieJar, UrlDb, Validators, WordDb};
+use crate::crawl::{
+    har_starting_url, is_pem, parse_credentials, parse_proxy_url, parse_selector, redact_proxy_url,
+    warc_starting_url, HttpAuth, LoginForm, ProxySettings, StatusRanges, TlsSettings, UrlPattern,
+    UrlScope, DEFAULT_TRACKING_PARAMS,
+};
 use crate::error::Error;
+use crate::extract::{Document, ExtractOptions};
 use crate::utils;
 
-use super::{Cli, FilterArg, SitePolicyArg};
+use super::{Cli, FilterArg, LinkArg, SitePolicyArg, StdinArg, TlsVersionArg};
+
+/// Headers holding credentials; never written to the state file.
+const SENSITIVE_HEADERS: [&str; 5] = [
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "x-api-key",
+    "x-auth-token",
+];
 
 /// Helper for json output URL file.
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
 pub struct State {
     pub starting_url: Url,
+    #[serde(default)]
+    pub seeds: Vec<Url>,
     pub depth_reached: usize,
     pub visited: Vec<String>,
     pub staged: Vec<String>,
     pub unvisited: Vec<String>,
     pub skipped: Vec<String>,
     pub errored: Vec<String>,
+    #[serde(default)]
+    pub oversized: Vec<String>,
+    #[serde(default)]
+    pub status_codes: BTreeMap<String, u16>,
+    #[serde(default)]
+    pub validators: BTreeMap<String, Validators>,
+    #[serde(default)]
+    pub requeues: BTreeMap<String, usize>,
+    #[serde(default)]
+    pub cookies: Vec<String>,
+    #[serde(default)]
+    pub warc_input: Option<String>,
+    #[serde(default)]
+    pub har_input: Option<String>,
     pub site_policy: SitePolicyArg,
     pub user_agent: Option<String>,
     pub headers: Option<Vec<(String, String)>>,
+    #[serde(default = "default_follow_links")]
+    pub follow_links: Vec<LinkArg>,
+    #[serde(default = "default_follow_rel")]
+    pub follow_rel: Vec<String>,
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    #[serde(default)]
+    pub include_glob: Vec<String>,
+    #[serde(default)]
+    pub exclude_glob: Vec<String>,
+    #[serde(default)]
+    pub proxy: Option<String>,
+    #[serde(default)]
+    pub http_proxy: Option<String>,
+    #[serde(default)]
+    pub https_proxy: Option<String>,
+    #[serde(default)]
+    pub no_proxy: Vec<String>,
+    #[serde(default)]
+    pub ca_cert: Vec<String>,
+    #[serde(default)]
+    pub client_cert: Option<String>,
+    #[serde(default)]
+    pub client_key: Option<String>,
+    #[serde(default)]
+    pub min_tls_version: Option<TlsVersionArg>,
+    #[serde(default)]
+    pub insecure: bool,
+    #[serde(default)]
+    pub ignore_robots: bool,
+    #[serde(default)]
+    pub sitemaps: bool,
     pub filters: Vec<FilterArg>,
     pub depth: usize,
     pub include_js: bool,
@@ -33,6 +102,34 @@ pub struct State {
     pub max_word_length: usize,
     pub req_per_sec: u64,
     pub limit_concurrent: usize,
+    #[serde(default)]
+    pub host_req_per_sec: Option<u64>,
+    #[serde(default)]
+    pub host_limit_concurrent: Option<usize>,
+    #[serde(default = "default_max_retries")]
+    pub max_retries: usize,
+    #[serde(default = "default_retry_backoff")]
+    pub retry_backoff: u64,
+    #[serde(default = "default_max_retry_delay")]
+    pub max_retry_delay: u64,
+    #[serde(default = "default_connect_timeout")]
+    pub connect_timeout: u64,
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+    #[serde(default = "default_max_redirects")]
+    pub max_redirects: usize,
+    #[serde(default = "default_accept_status")]
+    pub accept_status: Vec<(u16, u16)>,
+    #[serde(default)]
+    pub max_doc_size: u64,
+    #[serde(default)]
+    pub truncate_oversized: bool,
+    #[serde(default = "default_strip_params")]
+    pub strip_params: Vec<String>,
+    #[serde(default)]
+    pub keep_query_order: bool,
+    #[serde(default)]
+    pub fold_trailing_slash: bool,
 }
 
 impl State {
@@ -40,15 +137,40 @@ impl State {
     pub fn new(url: Url) -> Self {
         Self {
             starting_url: url.clone(),
+            seeds: vec![url.clone()],
             depth_reached: 0,
             visited: Vec::new(),
             staged: Vec::new(),
             unvisited: Vec::new(),
             skipped: Vec::new(),
             errored: Vec::new(),
+            oversized: Vec::new(),
+            status_codes: BTreeMap::new(),
+            validators: BTreeMap::new(),
+            requeues: BTreeMap::new(),
+            cookies: Vec::new(),
+            warc_input: None,
+            har_input: None,
             site_policy: SitePolicyArg::Same,
             user_agent: None,
             headers: None,
+            follow_links: default_follow_links(),
+            follow_rel: default_follow_rel(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            include_glob: Vec::new(),
+            exclude_glob: Vec::new(),
+            proxy: None,
+            http_proxy: None,
+            https_proxy: None,
+            no_proxy: Vec::new(),
+            ca_cert: Vec::new(),
+            client_cert: None,
+            client_key: None,
+            min_tls_version: None,
+            insecure: false,
+            ignore_robots: false,
+            sitemaps: false,
             filters: Vec::new(),
             depth: 1,
             include_js: false,
@@ -57,6 +179,20 @@ impl State {
             max_word_length: usize::MAX,
             req_per_sec: 5,
             limit_concurrent: 5,
+            host_req_per_sec: None,
+            host_limit_concurrent: None,
+            max_retries: default_max_retries(),
+            retry_backoff: default_retry_backoff(),
+            max_retry_delay: default_max_retry_delay(),
+            connect_timeout: default_connect_timeout(),
+            timeout: default_timeout(),
+            max_redirects: default_max_redirects(),
+            accept_status: default_accept_status(),
+            max_doc_size: 0,
+            truncate_oversized: false,
+            strip_params: default_strip_params(),
+            keep_query_order: false,
+            fold_trailing_slash: false,
         }
     }
 
@@ -69,6 +205,51 @@ impl State {
     }
 }
 
+fn default_max_retries() -> usize {
+    3
+}
+
+fn default_retry_backoff() -> u64 {
+    500
+}
+
+fn default_max_retry_delay() -> u64 {
+    60
+}
+
+fn default_connect_timeout() -> u64 {
+    5
+}
+
+fn default_timeout() -> u64 {
+    10
+}
+
+fn default_max_redirects() -> usize {
+    10
+}
+
+fn default_accept_status() -> Vec<(u16, u16)> {
+    vec![(200, 299)]
+}
+
+fn default_follow_links() -> Vec<LinkArg> {
+    vec![
+        LinkArg::Anchor,
+        LinkArg::Frame,
+        LinkArg::Form,
+        LinkArg::Refresh,
+    ]
+}
+
+fn default_follow_rel() -> Vec<String> {
+    vec!["next".to_string(), "prev".to_string()]
+}
+
+fn default_strip_params() -> Vec<String> {
+    DEFAULT_TRACKING_PARAMS.map(String::from).to_vec()
+}
+
 pub fn str_not_whitespace_parser() -> ValueParser {
     ValueParser::new(str_not_whitespace)
 }
@@ -96,6 +277,243 @@ pub fn parse_key_val(s: &str) -> Result<(String, String), Error> {
         ))
 }
 
+pub fn regex_parser() -> ValueParser {
+    ValueParser::new(parse_regex)
+}
+
+pub fn parse_regex(s: &str) -> Result<String, Error> {
+    UrlPattern::regex(s)?;
+    Ok(s.to_string())
+}
+
+/// Build the crawl scope from include and exclude patterns.
+pub fn build_scope(args: &Cli) -> Result<UrlScope, Error> {
+    let mut include = Vec::new();
+    for p in args.include.iter() {
+        include.push(UrlPattern::regex(p)?);
+    }
+    for p in args.include_glob.iter() {
+        include.push(UrlPattern::glob(p)?);
+    }
+    let mut exclude = Vec::new();
+    for p in args.exclude.iter() {
+        exclude.push(UrlPattern::regex(p)?);
+    }
+    for p in args.exclude_glob.iter() {
+        exclude.push(UrlPattern::glob(p)?);
+    }
+    Ok(UrlScope::new(include, exclude))
+}
+
+pub fn proxy_parser() -> ValueParser {
+    ValueParser::new(parse_proxy)
+}
+
+pub fn parse_proxy(s: &str) -> Result<String, Error> {
+    Ok(parse_proxy_url(s)?.to_string())
+}
+
+pub fn proxy_auth_parser() -> ValueParser {
+    ValueParser::new(parse_proxy_auth)
+}
+
+pub fn parse_proxy_auth(s: &str) -> Result<(String, String), Error> {
+    s.split_once(':')
+        .map(|(user, pass)| (user.to_owned(), pass.to_owned()))
+        .ok_or(Error::GeneralError(
+            "invalid proxy credentials format; use 'user:password'".to_string(),
+        ))
+}
+
+pub fn login_field_parser() -> ValueParser {
+    ValueParser::new(parse_login_field)
+}
+
+pub fn parse_login_field(s: &str) -> Result<(String, String), Error> {
+    match s.split_once('=') {
+        Some((name, val)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_owned(), val.to_owned()))
+        }
+        _ => Err(Error::GeneralError(
+            "invalid login field format; use 'name=value'".to_string(),
+        )),
+    }
+}
+
+pub fn selector_parser() -> ValueParser {
+    ValueParser::new(parse_css_selector)
+}
+
+pub fn parse_css_selector(s: &str) -> Result<String, Error> {
+    parse_selector(s)?;
+    Ok(s.to_string())
+}
+
+pub fn credentials_parser() -> ValueParser {
+    ValueParser::new(parse_credentials)
+}
+
+/// Build the HTTP authentication from auth args, scoped to the origins of
+/// the target urls (seeds).
+pub fn build_auth(args: &Cli, targets: &[Url]) -> Option<HttpAuth> {
+    let mut auth = match (&args.auth, &args.bearer_token) {
+        (Some((user, pass)), _) => HttpAuth::password(user, pass, args.auth_type.to_mode()),
+        (None, Some(token)) => HttpAuth::bearer(token),
+        (None, None) => return None,
+    };
+    for t in targets {
+        auth.add_scope(t);
+    }
+    Some(auth)
+}
+
+/// Returns the given headers without those holding credentials, for saving
+/// to state.
+pub fn redact_headers(headers: &Option<Vec<(String, String)>>) -> Option<Vec<(String, String)>> {
+    headers.as_ref().map(|h| {
+        h.iter()
+            .filter(|(k, _)| !is_sensitive_header(k))
+            .cloned()
+            .collect()
+    })
+}
+
+/// Returns the headers to resume with; headers holding credentials are not
+/// saved to state, so any given are kept alongside the saved headers.
+fn restore_headers(
+    given: &Option<Vec<(String, String)>>,
+    saved: &Option<Vec<(String, String)>>,
+) -> Option<Vec<(String, String)>> {
+    let mut headers: Vec<(String, String)> = saved.clone().unwrap_or_default();
+    headers.extend(
+        given
+            .iter()
+            .flatten()
+            .filter(|(k, _)| is_sensitive_header(k))
+            .cloned(),
+    );
+    if headers.is_empty() && saved.is_none() {
+        return None;
+    }
+    Some(headers)
+}
+
+/// Returns whether or not the named header holds credentials.
+fn is_sensitive_header(name: &str) -> bool {
+    SENSITIVE_HEADERS.contains(&name.trim().to_lowercase().as_str())
+}
+
+/// Build the form login from login args, resolving the login url against
+/// the target url.
+pub fn build_login(args: &Cli, target: &Url) -> Result<Option<LoginForm>, Error> {
+    let url = match &args.login_url {
+        Some(u) => target.join(u)?,
+        None => return Ok(None),
+    };
+    let mut login = LoginForm::new(url, args.login_field.clone());
+    if let Some(s) = &args.login_form {
+        login.set_selector(s)?;
+    }
+    if let Some(r) = &args.login_success {
+        login.set_success(Regex::new(r)?);
+    }
+    if !args.login_success_status.is_empty() {
+        login.set_success_status(StatusRanges::new(args.login_success_status.clone()));
+    }
+    if let Some(r) = &args.logged_out {
+        login.set_logged_out(Regex::new(r)?);
+    }
+    Ok(Some(login))
+}
+
+/// Build the proxy settings from proxy args.
+pub fn build_proxy(args: &Cli) -> Result<ProxySettings, Error> {
+    let parse = |p: &Option<String>| p.as_deref().map(parse_proxy_url).transpose();
+    let mut proxy = ProxySettings::new(
+        parse(&args.proxy)?,
+        parse(&args.http_proxy)?,
+        parse(&args.https_proxy)?,
+    );
+    if let Some((user, pass)) = &args.proxy_auth {
+        proxy.set_auth(user, pass);
+    }
+    proxy.set_no_proxy(args.no_proxy.clone());
+    Ok(proxy)
+}
+
+/// Returns the given proxy url without credentials, for saving to state.
+pub fn redact_proxy(proxy: &Option<String>) -> Option<String> {
+    proxy.as_ref().map(|p| match parse_proxy_url(p) {
+        Ok(u) => redact_proxy_url(&u).to_string(),
+        Err(_) => p.clone(),
+    })
+}
+
+/// Returns the proxy to resume with; credentials are not saved to state, so
+/// the given proxy is kept when it only differs from the saved one by them.
+fn restore_proxy(given: &Option<String>, saved: &Option<String>) -> Option<String> {
+    if given.is_some() && redact_proxy(given) == *saved {
+        given.clone()
+    } else {
+        saved.clone()
+    }
+}
+
+/// Build the TLS settings from TLS args, reading any certificates and keys.
+pub fn build_tls(args: &Cli) -> Result<TlsSettings, Error> {
+    let mut tls = TlsSettings::new();
+    for path in args.ca_cert.iter() {
+        let added = tls.add_root_certs(&read_file(path)?)?;
+        info!("trusting {} root certificate(s) from: {}", added, path);
+    }
+    if let Some(path) = &args.client_cert {
+        let cert = read_file(path)?;
+        if is_pem(&cert) {
+            let key = args.client_key.as_deref().map(read_file).transpose()?;
+            tls.set_identity_pem(&cert, key.as_deref())?;
+        } else {
+            let password = args.client_cert_password.as_deref().unwrap_or("");
+            tls.set_identity_pkcs12(&cert, password)?;
+        }
+        info!("using client certificate: {}", path);
+    }
+    if let Some(v) = args.min_tls_version {
+        tls.set_min_version(v.to_mode());
+    }
+    tls.set_insecure(args.insecure);
+    Ok(tls)
+}
+
+/// Read the given file, noting the path on failure.
+fn read_file(path: &str) -> Result<Vec<u8>, Error> {
+    fs::read(path).map_err(|e| Error::GeneralError(format!("unable to read '{}': {}", path, e)))
+}
+
+pub fn status_range_parser() -> ValueParser {
+    ValueParser::new(parse_status_range)
+}
+
+pub fn parse_status_range(s: &str) -> Result<(u16, u16), Error> {
+    let (lo, hi) = s.split_once('-').unwrap_or((s, s));
+    let parse = |v: &str| -> Result<u16, Error> {
+        match v.trim().parse::<u16>() {
+            Ok(code) if (100..=999).contains(&code) => Ok(code),
+            _ => Err(Error::GeneralError(format!(
+                "invalid status code '{}'; use a code (e.g. 200) or range (e.g. 200-299)",
+                v
+            ))),
+        }
+    };
+    let (lo, hi) = (parse(lo)?, parse(hi)?);
+    if lo > hi {
+        return Err(Error::GeneralError(format!(
+            "invalid status code range '{}'; start must not exceed end",
+            s
+        )));
+    }
+    Ok((lo, hi))
+}
+
 /// Helper for url parsing, predominantly to wrap errors.
 pub fn parse_url(url_str: &str) -> Result<Url, Error> {
     let res = Url::parse(url_str);
@@ -132,17 +550,47 @@ pub fn parse_headers(hashmap: &Option<Vec<(String, String)>>) -> Result<Option<H
     }
 }
 
-/// Helper for parsing a Target from cli args into a Url.
-pub fn parse_target(args: &Cli) -> Result<Url, Error> {
+/// Helper for parsing a Target from cli args into the Urls to start crawling
+/// from (seeds).
+pub fn parse_targets(args: &Cli) -> Result<Vec<Url>, Error> {
     let t = &args.target;
-    if let Some(url_str) = t.url.as_deref() {
+    if let Some(w) = t.warc_input.as_deref() {
+        return match warc_starting_url(w) {
+            Err(e) => {
+                warn!("error reading target warc file {}", w);
+                Err(e)
+            }
+            Ok(Some(u)) => Ok(vec![u]),
+            Ok(None) => Err(Error::GeneralError(format!(
+                "no responses found in warc file {}",
+                w
+            ))),
+        };
+    }
+
+    if let Some(h) = t.har_input.as_deref() {
+        return match har_starting_url(h) {
+            Err(e) => {
+                warn!("error reading target har file {}", h);
+                Err(e)
+            }
+            Ok(Some(u)) => Ok(vec![u]),
+            Ok(None) => Err(Error::GeneralError(format!(
+                "no entries found in har file {}",
+                h
+            ))),
+        };
+    }
+
+    let mut seeds = Vec::new();
+    for url_str in t.url.iter() {
         let res = parse_url(url_str);
         match res {
             Err(e) => {
                 warn!("error parsing target url {}", url_str);
                 return Err(e);
             }
-            Ok(u) => return Ok(u),
+            Ok(u) => seeds.push(u),
         }
     }
 
@@ -153,37 +601,169 @@ pub fn parse_target(args: &Cli) -> Result<Url, Error> {
                 warn!("error parsing target theme url {}", t.as_str());
                 return Err(e);
             }
-            Ok(u) => return Ok(u),
+            Ok(u) => seeds.push(u),
         }
     }
 
-    if let Some(p) = t.path.as_deref() {
-        let res = utils::url_from_path_str(&p);
+    for p in t.path.iter() {
+        let res = utils::url_from_path_str(p);
         match res {
             Err(e) => {
                 warn!("error parsing target path {} as url", p);
-                Err(e)
+                return Err(e);
             }
-            Ok(u) => parse_url(&u.as_str()),
+            Ok(u) => seeds.push(u),
         }
-    } else {
-        Err(Error::GeneralError(
+    }
+
+    if let Some(f) = t.seed_file.as_deref() {
+        seeds.extend(read_seed_file(f)?);
+    }
+
+    if t.stdin == Some(StdinArg::Urls) {
+        seeds.extend(read_stdin_seeds()?);
+    }
+
+    // the same seed given twice is crawled once
+    let mut seen = HashSet::new();
+    seeds.retain(|s| seen.insert(s.clone()));
+    if seeds.is_empty() {
+        return Err(Error::GeneralError(
             "no valid crawl target detected".to_string(),
-        ))
+        ));
+    }
+    Ok(seeds)
+}
+
+/// Helper for parsing a seed into a Url; urls of the http(s) and file
+/// schemes are taken as they are, existing local paths are taken as files,
+/// and host names (as listed by tools enumerating subdomains) are crawled
+/// over https.
+pub fn parse_seed(s: &str) -> Result<Url, Error> {
+    if let Ok(u) = Url::parse(s) {
+        if matches!(u.scheme(), "http" | "https" | "file") {
+            return Ok(u);
+        }
+    }
+    if Path::new(s).exists() {
+        return utils::url_from_path_str(s);
+    }
+    match Url::parse(&format!("https://{}", s)) {
+        Ok(u)
+            if !s.contains(char::is_whitespace)
+                && u.host_str()
+                    .is_some_and(|h| h.contains('.') || h == "localhost") =>
+        {
+            Ok(u)
+        }
+        // not a host; reports the missing path
+        _ => utils::url_from_path_str(s),
     }
 }
 
+/// Returns the seeds listed in a file, one per line; blank lines and lines
+/// starting with '#' are skipped.
+pub fn read_seed_file(file: &str) -> Result<Vec<Url>, Error> {
+    parse_seeds(&fs::read_to_string(file)?, file)
+}
+
+/// Returns the seeds read from stdin, listed as in a seed file.
+pub fn read_stdin_seeds() -> Result<Vec<Url>, Error> {
+    let mut contents = String::new();
+    io::stdin().read_to_string(&mut contents)?;
+    parse_seeds(&contents, "stdin")
+}
+
+/// Returns the seeds listed in the contents of the given source, one per
+/// line; blank lines and lines starting with '#' are skipped.
+fn parse_seeds(contents: &str, source: &str) -> Result<Vec<Url>, Error> {
+    let mut seeds = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let seed = parse_seed(line).map_err(|e| {
+            Error::GeneralError(format!(
+                "invalid seed '{}' on line {} of {}: {}",
+                line,
+                i + 1,
+                source,
+                e
+            ))
+        })?;
+        seeds.push(seed);
+    }
+    Ok(seeds)
+}
+
+/// Returns the document read from stdin, observing the maximum document
+/// size; the document is extracted from as is, so no other target may be
+/// given.
+pub fn read_stdin_document(args: &Cli) -> Result<Document, Error> {
+    let t = &args.target;
+    if !t.url.is_empty() || t.theme.is_some() || !t.path.is_empty() || t.seed_file.is_some() {
+        return Err(Error::GeneralError(
+            "--stdin document cannot be combined with other targets".to_string(),
+        ));
+    }
+    let max_size = args.max_doc_size;
+    // read at most one byte past the limit, to tell whether it was exceeded
+    let limit = if max_size > 0 {
+        max_size.saturating_add(1)
+    } else {
+        u64::MAX
+    };
+    let mut buf = Vec::new();
+    io::stdin().take(limit).read_to_end(&mut buf)?;
+    let truncated = max_size > 0 && buf.len() as u64 > max_size;
+    if truncated {
+        if !args.truncate_oversized {
+            return Err(Error::OversizedError(max_size));
+        }
+        buf.truncate(max_size as usize);
+    }
+    let mut doc = Document::new(Bytes::from(buf));
+    doc.set_truncated(truncated);
+    Ok(doc)
+}
+
+/// Build the options for extracting words from documents.
+pub fn build_extract_options(args: &Cli) -> ExtractOptions {
+    ExtractOptions::new(
+        args.min_word_length,
+        args.max_word_length,
+        args.include_js,
+        args.include_css,
+        FilterArg::to_modes(&args.filters),
+    )
+}
+
 /// Build an initial State based on cli args.
 pub fn build_initial_state(args: &mut Cli) -> Result<State, Error> {
+    if args.refresh && !(args.target.resume || args.target.resume_strict) {
+        return Err(Error::GeneralError(
+            "--refresh requires --resume or --resume-strict".to_string(),
+        ));
+    }
     let state = if args.target.resume || args.target.resume_strict {
         info!(
             "resuming from state '{}' and dictionary '{}'",
             args.state_file, args.output
         );
-        State::new_from_file(args.state_file.as_str())?
+        let mut state = State::new_from_file(args.state_file.as_str())?;
+        if state.seeds.is_empty() {
+            state.seeds.push(state.starting_url.clone());
+        }
+        // the archive being crawled, if any, is part of the target
+        args.target.warc_input = state.warc_input.clone();
+        args.target.har_input = state.har_input.clone();
+        state
     } else {
-        let url = parse_target(&args)?;
-        State::new(url)
+        let seeds = parse_targets(args)?;
+        let mut state = State::new(seeds[0].clone());
+        state.seeds = seeds;
+        state
     };
 
     if args.target.resume_strict {
@@ -192,9 +772,40 @@ pub fn build_initial_state(args: &mut Cli) -> Result<State, Error> {
         args.include_css = state.include_css;
         args.site_policy = state.site_policy;
         args.user_agent = state.user_agent.clone();
-        args.header = state.headers.clone();
+        args.header = restore_headers(&args.header, &state.headers);
+        args.follow_links = state.follow_links.clone();
+        args.follow_rel = state.follow_rel.clone();
+        args.include = state.include.clone();
+        args.exclude = state.exclude.clone();
+        args.include_glob = state.include_glob.clone();
+        args.exclude_glob = state.exclude_glob.clone();
+        args.proxy = restore_proxy(&args.proxy, &state.proxy);
+        args.http_proxy = restore_proxy(&args.http_proxy, &state.http_proxy);
+        args.https_proxy = restore_proxy(&args.https_proxy, &state.https_proxy);
+        args.no_proxy = state.no_proxy.clone();
+        args.ca_cert = state.ca_cert.clone();
+        args.client_cert = state.client_cert.clone();
+        args.client_key = state.client_key.clone();
+        args.min_tls_version = state.min_tls_version;
+        args.insecure = state.insecure;
+        args.ignore_robots = state.ignore_robots;
+        args.sitemaps = state.sitemaps;
         args.req_per_sec = state.req_per_sec;
         args.limit_concurrent = state.limit_concurrent;
+        args.host_req_per_sec = state.host_req_per_sec;
+        args.host_limit_concurrent = state.host_limit_concurrent;
+        args.max_retries = state.max_retries;
+        args.retry_backoff = state.retry_backoff;
+        args.max_retry_delay = state.max_retry_delay;
+        args.connect_timeout = state.connect_timeout;
+        args.timeout = state.timeout;
+        args.max_redirects = state.max_redirects;
+        args.accept_status = state.accept_status.clone();
+        args.max_doc_size = state.max_doc_size;
+        args.truncate_oversized = state.truncate_oversized;
+        args.strip_params = state.strip_params.clone();
+        args.keep_query_order = state.keep_query_order;
+        args.fold_trailing_slash = state.fold_trailing_slash;
         args.min_word_length = state.min_word_length;
         args.max_word_length = state.max_word_length;
         args.filters = state.filters.clone();
@@ -225,6 +836,30 @@ pub fn fill_urldb_from_state(db: &mut UrlDb, s: &mut State) {
         .drain(0..)
         .into_iter()
         .for_each(|u| db.mark_errored(&u));
+    s.oversized.drain(0..).for_each(|u| db.mark_oversized(&u));
+    std::mem::take(&mut s.status_codes)
+        .into_iter()
+        .for_each(|(u, c)| db.set_status_code(&u, c));
+    std::mem::take(&mut s.validators)
+        .into_iter()
+        .for_each(|(u, v)| db.set_validators(&u, v));
+    std::mem::take(&mut s.requeues)
+        .into_iter()
+        .for_each(|(u, n)| db.set_requeues(&u, n));
+}
+
+// Popuplate cookie jar from state; state cookies are consumed.
+pub fn fill_cookiejar_from_state(jar: &mut CookieJar, s: &mut State) {
+    let lines = std::mem::take(&mut s.cookies);
+    if let Err(e) = jar.insert_netscape(lines.iter().map(|l| l.as_str())) {
+        warn!("failed restoring cookies from state: {}", e);
+    }
+}
+
+// Popuplate cookie jar from a Netscape cookies file.
+pub fn fill_cookiejar_from_file(jar: &mut CookieJar, file: &str) -> Result<usize, Error> {
+    let contents = fs::read_to_string(file)?;
+    jar.insert_netscape(contents.lines())
 }
 
 // Popuplate worddb from existing dictionary.
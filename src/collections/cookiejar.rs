@@ -0,0 +1,286 @@
+use cookie::time::OffsetDateTime;
+use cookie_store::{CookieDomain, CookieExpiration, CookieStore, RawCookie};
+use reqwest::header::HeaderValue;
+use reqwest::Url;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::error::Error;
+
+/// Prefix of domains in a Netscape cookies file marking `HttpOnly` cookies.
+const HTTP_ONLY_PREFIX: &str = "#HttpOnly_";
+
+/// Stores cookies set by, and sent to, crawled sites.
+#[derive(Debug)]
+pub struct CookieJar(Arc<Mutex<CookieStore>>);
+
+impl Clone for CookieJar {
+    /// Returns a clone/handle of the given CookieJar.
+    fn clone(&self) -> Self {
+        CookieJar(Arc::clone(&self.0))
+    }
+}
+
+impl Default for CookieJar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CookieJar {
+    /// Returns a new, empty CookieJar instance.
+    pub fn new() -> Self {
+        CookieJar(Arc::new(Mutex::new(CookieStore::new())))
+    }
+
+    /// Returns the underlying cookie store, locked.
+    fn store(&self) -> MutexGuard<'_, CookieStore> {
+        match self.0.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// Returns the number of unexpired cookies in the jar.
+    pub fn len(&self) -> usize {
+        self.store().iter_unexpired().count()
+    }
+
+    /// Returns whether or not the jar holds no unexpired cookies.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Inserts cookies from the lines of a Netscape cookies file (i.e. a
+    /// `cookies.txt` exported by browsers and curl), returning how many were
+    /// inserted; comments, blank lines, and expired cookies are skipped.
+    pub fn insert_netscape<'a>(
+        &mut self,
+        lines: impl IntoIterator<Item = &'a str>,
+    ) -> Result<usize, Error> {
+        let mut store = self.store();
+        let mut inserted = 0;
+        for (i, line) in lines.into_iter().enumerate() {
+            let line = line.trim_end_matches(['\r', '\n']);
+            let (line, http_only) = match line.strip_prefix(HTTP_ONLY_PREFIX) {
+                Some(l) => (l, true),
+                None => (line, false),
+            };
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields.len() != 7 {
+                return Err(Error::GeneralError(format!(
+                    "invalid cookie on line {}; expected 7 tab separated fields",
+                    i + 1
+                )));
+            }
+            let (cookie, url) = netscape_cookie(&fields, http_only).ok_or(Error::GeneralError(
+                format!("invalid cookie on line {}", i + 1),
+            ))?;
+            if cookie
+                .expires_datetime()
+                .is_some_and(|e| e <= OffsetDateTime::now_utc())
+            {
+                continue;
+            }
+            if store.insert_raw(&cookie, &url).is_ok() {
+                inserted += 1;
+            }
+        }
+        Ok(inserted)
+    }
+
+    /// Returns the unexpired cookies in the jar, including session cookies,
+    /// as lines of a Netscape cookies file.
+    pub fn netscape_lines(&self) -> Vec<String> {
+        let store = self.store();
+        let mut lines: Vec<String> = store
+            .iter_unexpired()
+            .filter_map(|c| {
+                let (domain, subdomains) = match &c.domain {
+                    CookieDomain::HostOnly(h) => (h.to_string(), "FALSE"),
+                    CookieDomain::Suffix(s) => (format!(".{}", s), "TRUE"),
+                    _ => return None,
+                };
+                let expires = match c.expires {
+                    CookieExpiration::AtUtc(t) => t.unix_timestamp(),
+                    CookieExpiration::SessionEnd => 0,
+                };
+                Some(format!(
+                    "{}{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    if c.http_only().unwrap_or(false) {
+                        HTTP_ONLY_PREFIX
+                    } else {
+                        ""
+                    },
+                    domain,
+                    subdomains,
+                    c.path.as_ref(),
+                    if c.secure().unwrap_or(false) {
+                        "TRUE"
+                    } else {
+                        "FALSE"
+                    },
+                    expires,
+                    c.name(),
+                    c.value()
+                ))
+            })
+            .collect();
+        lines.sort();
+        lines
+    }
+}
+
+/// Returns a cookie from the fields of a line of a Netscape cookies file,
+/// along with a url that the cookie may have been set by.
+fn netscape_cookie(fields: &[&str], http_only: bool) -> Option<(RawCookie<'static>, Url)> {
+    let domain = fields[0].trim();
+    let host = domain.trim_start_matches('.');
+    let subdomains = fields[1].trim().eq_ignore_ascii_case("TRUE");
+    let path = fields[2].trim();
+    let secure = fields[3].trim().eq_ignore_ascii_case("TRUE");
+    let expires: i64 = fields[4].trim().parse().ok()?;
+
+    let scheme = if secure { "https" } else { "http" };
+    let url = Url::parse(&format!("{}://{}{}", scheme, host, path)).ok()?;
+
+    let mut cookie = RawCookie::new(fields[5].to_string(), fields[6].to_string());
+    // without a domain, cookies are only sent to the exact host
+    if subdomains || domain.starts_with('.') {
+        cookie.set_domain(host.to_string());
+    }
+    cookie.set_path(path.to_string());
+    cookie.set_secure(secure);
+    cookie.set_http_only(http_only);
+    // 0 marks a session cookie
+    if expires > 0 {
+        cookie.set_expires(OffsetDateTime::from_unix_timestamp(expires).ok()?);
+    }
+    Some((cookie, url))
+}
+
+/// Cookies are stored from, and sent with, requests made by a client using the jar.
+impl reqwest::cookie::CookieStore for CookieJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        let cookies = cookie_headers
+            .filter_map(|v| v.to_str().ok())
+            .filter_map(|v| RawCookie::parse(v.to_string()).ok());
+        self.store().store_response_cookies(cookies, url);
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        let value = self
+            .store()
+            .get_request_values(url)
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<String>>()
+            .join("; ");
+        if value.is_empty() {
+            return None;
+        }
+        HeaderValue::from_str(&value).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::cookie::CookieStore as _;
+
+    const COOKIES: &str = "# Netscape HTTP Cookie File
+# comment
+
+.example.com\tTRUE\t/\tFALSE\t0\tsession\tabc123
+#HttpOnly_www.example.com\tFALSE\t/app\tTRUE\t4102444800\tsecret\txyz
+example.com\tFALSE\t/\tFALSE\t946684800\texpired\told
+";
+
+    /// Returns the cookie header sent to the given url, with cookies sorted.
+    fn cookie_header(jar: &CookieJar, url: &str) -> Option<String> {
+        jar.cookies(&Url::parse(url).ok().unwrap()).map(|v| {
+            let mut pairs: Vec<&str> = v.to_str().unwrap().split("; ").collect();
+            pairs.sort();
+            pairs.join("; ")
+        })
+    }
+
+    #[test]
+    fn netscape_import() {
+        let mut jar = CookieJar::new();
+        assert_eq!(jar.insert_netscape(COOKIES.lines()).ok(), Some(2));
+        assert_eq!(jar.len(), 2);
+        assert_eq!(
+            cookie_header(&jar, "http://sub.example.com/"),
+            Some("session=abc123".to_string())
+        );
+        assert_eq!(
+            cookie_header(&jar, "https://www.example.com/app/page"),
+            Some("secret=xyz; session=abc123".to_string())
+        );
+        // secure cookie over http, and path mismatch
+        assert_eq!(
+            cookie_header(&jar, "http://www.example.com/app/page"),
+            Some("session=abc123".to_string())
+        );
+        assert_eq!(
+            cookie_header(&jar, "https://www.example.com/other"),
+            Some("session=abc123".to_string())
+        );
+        assert_eq!(cookie_header(&jar, "https://example.org/"), None);
+    }
+
+    #[test]
+    fn netscape_round_trip() {
+        let mut jar = CookieJar::new();
+        jar.insert_netscape(COOKIES.lines()).ok().unwrap();
+        let lines = jar.netscape_lines();
+        assert_eq!(
+            lines,
+            vec![
+                "#HttpOnly_www.example.com\tFALSE\t/app\tTRUE\t4102444800\tsecret\txyz",
+                ".example.com\tTRUE\t/\tFALSE\t0\tsession\tabc123",
+            ]
+        );
+        let mut restored = CookieJar::new();
+        let inserted = restored
+            .insert_netscape(lines.iter().map(|l| l.as_str()))
+            .ok();
+        assert_eq!(inserted, Some(2));
+        assert_eq!(restored.netscape_lines(), lines);
+    }
+
+    #[test]
+    fn netscape_invalid() {
+        let mut jar = CookieJar::new();
+        assert!(jar.insert_netscape(["example.com\tFALSE\t/"]).is_err());
+        assert!(jar
+            .insert_netscape(["example.com\tFALSE\t/\tFALSE\tsoon\tname\tvalue"])
+            .is_err());
+        assert!(jar.is_empty());
+    }
+
+    #[test]
+    fn response_cookies() {
+        let jar = CookieJar::new();
+        let url = Url::parse("https://example.com/account/login")
+            .ok()
+            .unwrap();
+        let headers = [
+            HeaderValue::from_static("sid=1; Path=/; HttpOnly"),
+            HeaderValue::from_static("pref=dark; Max-Age=3600"),
+        ];
+        jar.set_cookies(&mut headers.iter(), &url);
+        assert_eq!(jar.len(), 2);
+        assert_eq!(
+            cookie_header(&jar, "https://example.com/"),
+            Some("sid=1".to_string())
+        );
+        let clone = jar.clone();
+        assert_eq!(
+            cookie_header(&clone, "https://example.com/account/settings"),
+            Some("pref=dark; sid=1".to_string())
+        );
+    }
+}
@@ -1,5 +1,7 @@
+mod cookiejar;
 mod urldb;
 mod worddb;
 
+pub use self::cookiejar::*;
 pub use self::urldb::*;
 pub use self::worddb::*;
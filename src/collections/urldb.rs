@@ -1,26 +1,79 @@
+use reqwest::header::{HeaderMap, ETAG, LAST_MODIFIED};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex, MutexGuard};
 
+/// Validators of a document, as served; sent back in conditional requests
+/// so that unchanged documents need not be downloaded again.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Validators {
+    /// Value of the `ETag` header.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    /// Value of the `Last-Modified` header.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    /// Returns the validators within the given response headers.
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let get = |name| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+        Self {
+            etag: get(ETAG),
+            last_modified: get(LAST_MODIFIED),
+        }
+    }
+
+    /// Returns whether or not there are no validators.
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
 /// Stores urls, tracking whether or not they have been visited.
 #[derive(Debug)]
-pub struct UrlDb(Arc<Mutex<HashMap<String, Status>>>);
+pub struct UrlDb {
+    urls: Arc<Mutex<HashMap<String, Status>>>,
+    /// HTTP status codes observed while visiting urls.
+    codes: Arc<Mutex<HashMap<String, u16>>>,
+    /// Validators of documents served while visiting urls.
+    validators: Arc<Mutex<HashMap<String, Validators>>>,
+    /// Number of times urls were re-queued after running out of retries.
+    requeues: Arc<Mutex<HashMap<String, usize>>>,
+}
 
 impl Clone for UrlDb {
     /// Returns a clone/handle of the given UrlDb.
     fn clone(&self) -> Self {
-        UrlDb(Arc::clone(&self.0))
+        UrlDb {
+            urls: Arc::clone(&self.urls),
+            codes: Arc::clone(&self.codes),
+            validators: Arc::clone(&self.validators),
+            requeues: Arc::clone(&self.requeues),
+        }
     }
 }
 
 impl UrlDb {
     /// Returns a new UrlDb instance.
     pub fn new() -> Self {
-        UrlDb(Arc::new(Mutex::new(HashMap::new())))
+        UrlDb {
+            urls: Arc::new(Mutex::new(HashMap::new())),
+            codes: Arc::new(Mutex::new(HashMap::new())),
+            validators: Arc::new(Mutex::new(HashMap::new())),
+            requeues: Arc::new(Mutex::new(HashMap::new())),
+        }
     }
 
     /// Returns an iterator over the urls that were discovered and visited.
     pub fn visited_urls_iter(&self) -> impl Iterator<Item = String> {
-        let hm: MutexGuard<HashMap<String, Status>> = match self.0.lock() {
+        let hm: MutexGuard<HashMap<String, Status>> = match self.urls.lock() {
             Ok(guard) => guard,
             Err(poisoned) => poisoned.into_inner(),
         };
@@ -32,7 +85,7 @@ impl UrlDb {
 
     /// Returns an iterator over the urls that are currently staged.
     pub fn staged_urls_iter(&self) -> impl Iterator<Item = String> {
-        let hm: MutexGuard<HashMap<String, Status>> = match self.0.lock() {
+        let hm: MutexGuard<HashMap<String, Status>> = match self.urls.lock() {
             Ok(guard) => guard,
             Err(poisoned) => poisoned.into_inner(),
         };
@@ -44,7 +97,7 @@ impl UrlDb {
 
     /// Returns an iterator over the urls that were discovered, but unvisited.
     pub fn unvisited_urls_iter(&self) -> impl Iterator<Item = String> {
-        let hm: MutexGuard<HashMap<String, Status>> = match self.0.lock() {
+        let hm: MutexGuard<HashMap<String, Status>> = match self.urls.lock() {
             Ok(guard) => guard,
             Err(poisoned) => poisoned.into_inner(),
         };
@@ -56,7 +109,7 @@ impl UrlDb {
 
     /// Returns an iterator over the urls that were discovered, but skipped.
     pub fn skipped_urls_iter(&self) -> impl Iterator<Item = String> {
-        let hm: MutexGuard<HashMap<String, Status>> = match self.0.lock() {
+        let hm: MutexGuard<HashMap<String, Status>> = match self.urls.lock() {
             Ok(guard) => guard,
             Err(poisoned) => poisoned.into_inner(),
         };
@@ -69,7 +122,7 @@ impl UrlDb {
     /// Returns an iterator over the urls that were discovered, but encountered and error while
     /// visiting.
     pub fn errored_urls_iter(&self) -> impl Iterator<Item = String> {
-        let hm: MutexGuard<HashMap<String, Status>> = match self.0.lock() {
+        let hm: MutexGuard<HashMap<String, Status>> = match self.urls.lock() {
             Ok(guard) => guard,
             Err(poisoned) => poisoned.into_inner(),
         };
@@ -79,6 +132,19 @@ impl UrlDb {
             .map(|(k, _v)| k)
     }
 
+    /// Returns an iterator over the urls that were discovered, but exceeded the
+    /// maximum document size.
+    pub fn oversized_urls_iter(&self) -> impl Iterator<Item = String> {
+        let hm: MutexGuard<HashMap<String, Status>> = match self.urls.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        hm.clone()
+            .into_iter()
+            .filter(|(_k, v)| *v == Status::Oversized)
+            .map(|(k, _v)| k)
+    }
+
     /// Returns the number of urls that were visited.
     pub fn num_visited_urls(&self) -> usize {
         self.visited_urls_iter().count()
@@ -104,9 +170,14 @@ impl UrlDb {
         self.errored_urls_iter().count()
     }
 
+    /// Returns the number of urls that exceeded the maximum document size.
+    pub fn num_oversized_urls(&self) -> usize {
+        self.oversized_urls_iter().count()
+    }
+
     /// Inserts and marks a url as visited.
     pub fn mark_visited(&mut self, url: &str) -> () {
-        let mut hm: MutexGuard<HashMap<String, Status>> = match self.0.lock() {
+        let mut hm: MutexGuard<HashMap<String, Status>> = match self.urls.lock() {
             Ok(guard) => guard,
             Err(poisoned) => poisoned.into_inner(),
         };
@@ -115,7 +186,7 @@ impl UrlDb {
 
     /// Inserts and marks a url as staged.
     pub fn mark_staged(&mut self, url: &str) -> () {
-        let mut hm: MutexGuard<HashMap<String, Status>> = match self.0.lock() {
+        let mut hm: MutexGuard<HashMap<String, Status>> = match self.urls.lock() {
             Ok(guard) => guard,
             Err(poisoned) => poisoned.into_inner(),
         };
@@ -124,7 +195,7 @@ impl UrlDb {
 
     /// Inserts and marks a url as unvisited.
     pub fn mark_unvisited(&mut self, url: &str) -> () {
-        let mut hm: MutexGuard<HashMap<String, Status>> = match self.0.lock() {
+        let mut hm: MutexGuard<HashMap<String, Status>> = match self.urls.lock() {
             Ok(guard) => guard,
             Err(poisoned) => poisoned.into_inner(),
         };
@@ -133,7 +204,7 @@ impl UrlDb {
 
     /// Inserts and marks a url as skipped.
     pub fn mark_skipped(&mut self, url: &str) -> () {
-        let mut hm: MutexGuard<HashMap<String, Status>> = match self.0.lock() {
+        let mut hm: MutexGuard<HashMap<String, Status>> = match self.urls.lock() {
             Ok(guard) => guard,
             Err(poisoned) => poisoned.into_inner(),
         };
@@ -142,16 +213,25 @@ impl UrlDb {
 
     /// Inserts and marks a url as errored.
     pub fn mark_errored(&mut self, url: &str) -> () {
-        let mut hm: MutexGuard<HashMap<String, Status>> = match self.0.lock() {
+        let mut hm: MutexGuard<HashMap<String, Status>> = match self.urls.lock() {
             Ok(guard) => guard,
             Err(poisoned) => poisoned.into_inner(),
         };
         hm.insert(url.to_owned(), Status::Error);
     }
 
+    /// Inserts and marks a url as oversized.
+    pub fn mark_oversized(&mut self, url: &str) {
+        let mut hm: MutexGuard<HashMap<String, Status>> = match self.urls.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        hm.insert(url.to_owned(), Status::Oversized);
+    }
+
     /// Inserts and marks a url as visited, only if the url is new.
     pub fn cond_mark_visited(&mut self, url: &str) -> () {
-        let mut hm: MutexGuard<HashMap<String, Status>> = match self.0.lock() {
+        let mut hm: MutexGuard<HashMap<String, Status>> = match self.urls.lock() {
             Ok(guard) => guard,
             Err(poisoned) => poisoned.into_inner(),
         };
@@ -160,7 +240,7 @@ impl UrlDb {
 
     /// Inserts and marks a url as staged, only if the url is new.
     pub fn cond_mark_staged(&mut self, url: &str) -> () {
-        let mut hm: MutexGuard<HashMap<String, Status>> = match self.0.lock() {
+        let mut hm: MutexGuard<HashMap<String, Status>> = match self.urls.lock() {
             Ok(guard) => guard,
             Err(poisoned) => poisoned.into_inner(),
         };
@@ -169,7 +249,7 @@ impl UrlDb {
 
     /// Inserts and marks a url as unvisited, only if the url is new.
     pub fn cond_mark_unvisited(&mut self, url: &str) -> () {
-        let mut hm: MutexGuard<HashMap<String, Status>> = match self.0.lock() {
+        let mut hm: MutexGuard<HashMap<String, Status>> = match self.urls.lock() {
             Ok(guard) => guard,
             Err(poisoned) => poisoned.into_inner(),
         };
@@ -178,7 +258,7 @@ impl UrlDb {
 
     /// Inserts and marks a url as skipped, only if the url is new.
     pub fn cond_mark_skipped(&mut self, url: &str) -> () {
-        let mut hm: MutexGuard<HashMap<String, Status>> = match self.0.lock() {
+        let mut hm: MutexGuard<HashMap<String, Status>> = match self.urls.lock() {
             Ok(guard) => guard,
             Err(poisoned) => poisoned.into_inner(),
         };
@@ -187,16 +267,136 @@ impl UrlDb {
 
     /// Inserts and marks a url as errored, only if the url is new.
     pub fn cond_mark_errored(&mut self, url: &str) -> () {
-        let mut hm: MutexGuard<HashMap<String, Status>> = match self.0.lock() {
+        let mut hm: MutexGuard<HashMap<String, Status>> = match self.urls.lock() {
             Ok(guard) => guard,
             Err(poisoned) => poisoned.into_inner(),
         };
         hm.entry(url.to_owned()).or_insert(Status::Error);
     }
 
+    /// Records the HTTP status code observed while visiting a url.
+    pub fn set_status_code(&mut self, url: &str, code: u16) {
+        let mut hm: MutexGuard<HashMap<String, u16>> = match self.codes.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        hm.insert(url.to_owned(), code);
+    }
+
+    /// Returns the HTTP status code observed while visiting a url, if any.
+    pub fn status_code(&self, url: &str) -> Option<u16> {
+        let hm: MutexGuard<HashMap<String, u16>> = match self.codes.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        hm.get(url).copied()
+    }
+
+    /// Returns an iterator over urls and the HTTP status codes observed while
+    /// visiting them.
+    pub fn status_codes_iter(&self) -> impl Iterator<Item = (String, u16)> {
+        let hm: MutexGuard<HashMap<String, u16>> = match self.codes.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        hm.clone().into_iter()
+    }
+
+    /// Records the validators of the document served while visiting a url;
+    /// empty validators clear any previously recorded.
+    pub fn set_validators(&mut self, url: &str, validators: Validators) {
+        let mut hm: MutexGuard<HashMap<String, Validators>> = match self.validators.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if validators.is_empty() {
+            hm.remove(url);
+        } else {
+            hm.insert(url.to_owned(), validators);
+        }
+    }
+
+    /// Returns the validators of the document served while visiting a url, if any.
+    pub fn validators(&self, url: &str) -> Option<Validators> {
+        let hm: MutexGuard<HashMap<String, Validators>> = match self.validators.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        hm.get(url).cloned()
+    }
+
+    /// Returns an iterator over urls and the validators of the documents
+    /// served while visiting them.
+    pub fn validators_iter(&self) -> impl Iterator<Item = (String, Validators)> {
+        let hm: MutexGuard<HashMap<String, Validators>> = match self.validators.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        hm.clone().into_iter()
+    }
+
+    /// Records that a url was re-queued after running out of retries;
+    /// returns the number of times it has been re-queued, including this one.
+    pub fn add_requeue(&mut self, url: &str) -> usize {
+        let mut hm: MutexGuard<HashMap<String, usize>> = match self.requeues.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let n = hm.entry(url.to_owned()).or_insert(0);
+        *n += 1;
+        *n
+    }
+
+    /// Sets the number of times a url was re-queued; 0 clears it.
+    pub fn set_requeues(&mut self, url: &str, n: usize) {
+        let mut hm: MutexGuard<HashMap<String, usize>> = match self.requeues.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if n == 0 {
+            hm.remove(url);
+        } else {
+            hm.insert(url.to_owned(), n);
+        }
+    }
+
+    /// Returns an iterator over urls and the number of times they were
+    /// re-queued.
+    pub fn requeues_iter(&self) -> impl Iterator<Item = (String, usize)> {
+        let hm: MutexGuard<HashMap<String, usize>> = match self.requeues.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        hm.clone().into_iter()
+    }
+
+    /// Returns whether or not a url is yet to be visited; i.e. it is new, or
+    /// marked as unvisited or staged.
+    pub fn is_pending(&self, url: &str) -> bool {
+        let hm: MutexGuard<HashMap<String, Status>> = match self.urls.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        matches!(
+            hm.get(url),
+            None | Some(Status::Unvisited) | Some(Status::Staged)
+        )
+    }
+
+    /// Move all visited urls back to unvisited, so they are visited again.
+    pub fn unvisit_visited_urls(&mut self) {
+        let mut hm: MutexGuard<HashMap<String, Status>> = match self.urls.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        for v in hm.values_mut().filter(|v| **v == Status::Visited) {
+            *v = Status::Unvisited;
+        }
+    }
+
     /// Move all unvisited urls onto the stage.
     pub fn stage_unvisited_urls(&mut self) {
-        let mut hm: MutexGuard<HashMap<String, Status>> = match self.0.lock() {
+        let mut hm: MutexGuard<HashMap<String, Status>> = match self.urls.lock() {
             Ok(guard) => guard,
             Err(poisoned) => poisoned.into_inner(),
         };
@@ -222,6 +422,9 @@ enum Status {
     Skip,
     /// A Url of which an error was encountered while attempting to visit.
     Error,
+    /// A Url whose document exceeded the maximum size; it may have been
+    /// processed in part (truncated), or not at all.
+    Oversized,
 }
 
 impl PartialEq for Status {
@@ -232,6 +435,7 @@ impl PartialEq for Status {
             (Self::Unvisited, Self::Unvisited) => true,
             (Self::Skip, Self::Skip) => true,
             (Self::Error, Self::Error) => true,
+            (Self::Oversized, Self::Oversized) => true,
             _ => false,
         }
     }
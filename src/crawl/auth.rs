@@ -0,0 +1,626 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use md5::Md5;
+use reqwest::header::{HeaderMap, HeaderValue, WWW_AUTHENTICATE};
+use reqwest::{Method, Url};
+use sha2::{Digest, Sha256};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+use url::Origin;
+
+use crate::error::Error;
+
+/// Schemes of authentication challenges that are answered.
+#[derive(Copy, Debug, Clone, PartialEq)]
+pub enum AuthMode {
+    /// Answer Basic or Digest challenges, preferring Digest.
+    Any,
+    /// Send Basic credentials with every request, without awaiting a challenge.
+    Basic,
+    /// Answer only Digest challenges.
+    Digest,
+}
+
+/// Display implementation.
+impl std::fmt::Display for AuthMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Any => write!(f, "any"),
+            Self::Basic => write!(f, "basic"),
+            Self::Digest => write!(f, "digest"),
+        }
+    }
+}
+
+/// Credentials presented to servers.
+#[derive(Debug, Clone)]
+enum Credentials {
+    /// Username and password, for Basic or Digest authentication.
+    Password { username: String, password: String },
+    /// Token for Bearer authentication.
+    Bearer(String),
+}
+
+/// Hash algorithms of Digest authentication.
+#[derive(Copy, Debug, Clone, PartialEq)]
+enum DigestAlgorithm {
+    Md5,
+    Sha256,
+}
+
+impl DigestAlgorithm {
+    /// Returns the lowercase hex encoded hash of the given data.
+    fn hash(self, data: &str) -> String {
+        let bytes: Vec<u8> = match self {
+            Self::Md5 => Md5::digest(data.as_bytes()).to_vec(),
+            Self::Sha256 => Sha256::digest(data.as_bytes()).to_vec(),
+        };
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Returns the name of the algorithm, as used within challenges.
+    fn name(self) -> &'static str {
+        match self {
+            Self::Md5 => "MD5",
+            Self::Sha256 => "SHA-256",
+        }
+    }
+}
+
+/// Parameters of a Digest challenge (RFC 7616), along with the number of
+/// requests made using its nonce.
+#[derive(Debug, Clone, PartialEq)]
+struct DigestChallenge {
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    algorithm: DigestAlgorithm,
+    /// Whether the session variant (e.g. `MD5-sess`) of the algorithm is used.
+    session: bool,
+    /// Whether `qop=auth` was offered; `auth-int` is not supported.
+    qop_auth: bool,
+    /// Whether the previous nonce was rejected only for being stale.
+    stale: bool,
+    nonce_count: u32,
+}
+
+/// Authentication challenges that are answered.
+#[derive(Debug, Clone, PartialEq)]
+enum Challenge {
+    Basic,
+    Digest(DigestChallenge),
+}
+
+/// HTTP authentication for requests; credentials are only presented to the
+/// origins (scheme, host, and port) in scope, such as that of the target,
+/// so they are not sent to links followed off-site.
+#[derive(Debug, Clone)]
+pub struct HttpAuth {
+    /// Credentials presented to servers.
+    credentials: Credentials,
+    /// Schemes of challenges that are answered.
+    mode: AuthMode,
+    /// Origins that credentials are presented to.
+    scope: Vec<Origin>,
+    /// Most recent challenge from each origin, shared among clones.
+    challenges: Arc<Mutex<HashMap<String, Challenge>>>,
+}
+
+impl HttpAuth {
+    /// Returns a new HttpAuth instance presenting the given username and
+    /// password, for Basic or Digest authentication.
+    pub fn password(username: &str, password: &str, mode: AuthMode) -> Self {
+        Self::new(
+            Credentials::Password {
+                username: username.to_string(),
+                password: password.to_string(),
+            },
+            mode,
+        )
+    }
+
+    /// Returns a new HttpAuth instance presenting the given bearer token;
+    /// the token is sent with every request in scope.
+    pub fn bearer(token: &str) -> Self {
+        Self::new(Credentials::Bearer(token.to_string()), AuthMode::Any)
+    }
+
+    fn new(credentials: Credentials, mode: AuthMode) -> Self {
+        Self {
+            credentials,
+            mode,
+            scope: Vec::new(),
+            challenges: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Add the origin of the given url to the scope that credentials are
+    /// presented to.
+    pub fn add_scope(&mut self, url: &Url) {
+        let origin = url.origin();
+        if !self.scope.contains(&origin) {
+            self.scope.push(origin);
+        }
+    }
+
+    /// Returns whether or not credentials may be presented to the given url.
+    pub fn in_scope(&self, url: &Url) -> bool {
+        self.scope.contains(&url.origin())
+    }
+
+    /// Returns the challenges seen so far, locked.
+    fn challenges(&self) -> MutexGuard<'_, HashMap<String, Challenge>> {
+        match self.challenges.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// Returns the `Authorization` header to send with a request for the
+    /// given url, if any; for Basic and Digest authentication, a challenge
+    /// must have been seen from the url's origin, unless sending Basic
+    /// credentials unprompted.
+    pub fn authorization(&self, method: &Method, url: &Url) -> Option<HeaderValue> {
+        if !self.in_scope(url) {
+            return None;
+        }
+        let value = match &self.credentials {
+            Credentials::Bearer(token) => format!("Bearer {}", token),
+            Credentials::Password { username, password } => {
+                let mut challenges = self.challenges();
+                let origin = url.origin().ascii_serialization();
+                match challenges.get_mut(&origin) {
+                    Some(Challenge::Digest(d)) => {
+                        digest_response(d, username, password, method, url)
+                    }
+                    Some(Challenge::Basic) => basic_response(username, password),
+                    None if self.mode == AuthMode::Basic => basic_response(username, password),
+                    None => return None,
+                }
+            }
+        };
+        let mut value = HeaderValue::from_str(&value).ok()?;
+        value.set_sensitive(true);
+        Some(value)
+    }
+
+    /// Record the challenge within the headers of a 401 response for the
+    /// given url, returning whether the request should be retried; that is,
+    /// when credentials were not yet sent, or a Digest nonce was stale.
+    pub fn observe_challenge(&self, url: &Url, headers: &HeaderMap, sent: bool) -> bool {
+        if !self.in_scope(url) || matches!(self.credentials, Credentials::Bearer(_)) {
+            return false;
+        }
+        let offered: Vec<Challenge> = headers
+            .get_all(WWW_AUTHENTICATE)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(parse_challenges)
+            .filter(|c| match c {
+                Challenge::Basic => self.mode != AuthMode::Digest,
+                Challenge::Digest(_) => self.mode != AuthMode::Basic,
+            })
+            .collect();
+        // prefer digest over basic, and sha-256 over md5
+        let challenge = offered
+            .iter()
+            .filter_map(|c| match c {
+                Challenge::Digest(d) => Some((d.algorithm == DigestAlgorithm::Sha256, c)),
+                Challenge::Basic => None,
+            })
+            .max_by_key(|(sha, _)| *sha)
+            .map(|(_, c)| c)
+            .or(offered.first());
+        let challenge = match challenge {
+            Some(c) => c.clone(),
+            None => return false,
+        };
+        let retry = !sent || matches!(&challenge, Challenge::Digest(d) if d.stale);
+        self.challenges()
+            .insert(url.origin().ascii_serialization(), challenge);
+        retry
+    }
+}
+
+/// Returns the value of a Basic `Authorization` header.
+fn basic_response(username: &str, password: &str) -> String {
+    format!(
+        "Basic {}",
+        STANDARD.encode(format!("{}:{}", username, password))
+    )
+}
+
+/// Returns the value of a Digest `Authorization` header for the given
+/// challenge, counting the use of its nonce.
+fn digest_response(
+    challenge: &mut DigestChallenge,
+    username: &str,
+    password: &str,
+    method: &Method,
+    url: &Url,
+) -> String {
+    let alg = challenge.algorithm;
+    challenge.nonce_count += 1;
+    let nc = format!("{:08x}", challenge.nonce_count);
+    let cnonce = cnonce();
+    let uri = match url.query() {
+        Some(q) => format!("{}?{}", url.path(), q),
+        None => url.path().to_string(),
+    };
+
+    let mut ha1 = alg.hash(&format!("{}:{}:{}", username, challenge.realm, password));
+    if challenge.session {
+        ha1 = alg.hash(&format!("{}:{}:{}", ha1, challenge.nonce, cnonce));
+    }
+    let ha2 = alg.hash(&format!("{}:{}", method.as_str(), uri));
+    let response = if challenge.qop_auth {
+        alg.hash(&format!(
+            "{}:{}:{}:{}:auth:{}",
+            ha1, challenge.nonce, nc, cnonce, ha2
+        ))
+    } else {
+        alg.hash(&format!("{}:{}:{}", ha1, challenge.nonce, ha2))
+    };
+
+    let mut value = format!(
+        "Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\", algorithm={}{}, response=\"{}\"",
+        quote(username),
+        quote(&challenge.realm),
+        quote(&challenge.nonce),
+        quote(&uri),
+        alg.name(),
+        if challenge.session { "-sess" } else { "" },
+        response
+    );
+    if challenge.qop_auth {
+        value.push_str(&format!(", qop=auth, nc={}, cnonce=\"{}\"", nc, cnonce));
+    }
+    if let Some(opaque) = &challenge.opaque {
+        value.push_str(&format!(", opaque=\"{}\"", quote(opaque)));
+    }
+    value
+}
+
+/// Returns a random client nonce.
+fn cnonce() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    hasher.write_u128(nanos);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Returns the given value escaped for use within a quoted string.
+fn quote(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Parses the supported challenges from the value of a `WWW-Authenticate`
+/// header, which may hold several challenges separated by commas.
+fn parse_challenges(value: &str) -> Vec<Challenge> {
+    let mut challenges = Vec::new();
+    let mut current: Option<(String, HashMap<String, String>)> = None;
+    for item in split_params(value) {
+        let item = item.trim();
+        if item.is_empty() {
+            continue;
+        }
+        // a new challenge begins with a scheme, optionally followed by a param
+        let (scheme, param) = match item.split_once(char::is_whitespace) {
+            Some((s, p)) if !s.contains('=') => (Some(s), p.trim()),
+            _ if !item.contains('=') => (Some(item), ""),
+            _ => (None, item),
+        };
+        if let Some(s) = scheme {
+            challenges.extend(current.take().and_then(to_challenge));
+            current = Some((s.to_lowercase(), HashMap::new()));
+        }
+        if let (Some((_, params)), Some((k, v))) = (current.as_mut(), param.split_once('=')) {
+            params.insert(k.trim().to_lowercase(), unquote(v.trim()));
+        }
+    }
+    challenges.extend(current.and_then(to_challenge));
+    challenges
+}
+
+/// Returns the challenge of the given scheme and params, if supported.
+fn to_challenge((scheme, params): (String, HashMap<String, String>)) -> Option<Challenge> {
+    match scheme.as_str() {
+        "basic" => Some(Challenge::Basic),
+        "digest" => {
+            let algorithm = params
+                .get("algorithm")
+                .map(|a| a.to_uppercase())
+                .unwrap_or("MD5".to_string());
+            let (algorithm, session) = match algorithm.as_str() {
+                "MD5" => (DigestAlgorithm::Md5, false),
+                "MD5-SESS" => (DigestAlgorithm::Md5, true),
+                "SHA-256" => (DigestAlgorithm::Sha256, false),
+                "SHA-256-SESS" => (DigestAlgorithm::Sha256, true),
+                _ => return None,
+            };
+            let qop = params.get("qop");
+            let qop_auth = qop.is_some_and(|q| q.split(',').any(|q| q.trim() == "auth"));
+            // only auth-int offered
+            if qop.is_some() && !qop_auth {
+                return None;
+            }
+            Some(Challenge::Digest(DigestChallenge {
+                realm: params.get("realm").cloned().unwrap_or_default(),
+                nonce: params.get("nonce")?.clone(),
+                opaque: params.get("opaque").cloned(),
+                algorithm,
+                session,
+                qop_auth,
+                stale: params
+                    .get("stale")
+                    .is_some_and(|s| s.eq_ignore_ascii_case("true")),
+                nonce_count: 0,
+            }))
+        }
+        _ => None,
+    }
+}
+
+/// Splits the given header value on commas outside of quoted strings.
+fn split_params(value: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut item = String::new();
+    let mut quoted = false;
+    let mut escaped = false;
+    for c in value.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                items.push(std::mem::take(&mut item));
+                continue;
+            }
+            _ => {}
+        }
+        item.push(c);
+    }
+    items.push(item);
+    items
+}
+
+/// Returns the given param value with any quotes and escapes removed.
+fn unquote(value: &str) -> String {
+    match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(v) => {
+            let mut out = String::new();
+            let mut escaped = false;
+            for c in v.chars() {
+                if c == '\\' && !escaped {
+                    escaped = true;
+                    continue;
+                }
+                escaped = false;
+                out.push(c);
+            }
+            out
+        }
+        None => value.to_string(),
+    }
+}
+
+/// Parses credentials given as `username:password`.
+pub fn parse_credentials(s: &str) -> Result<(String, String), Error> {
+    s.split_once(':')
+        .map(|(user, pass)| (user.to_owned(), pass.to_owned()))
+        .ok_or(Error::GeneralError(
+            "invalid credentials format; use 'user:password'".to_string(),
+        ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).ok().unwrap()
+    }
+
+    fn challenge_headers(values: &[&str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for v in values {
+            headers.append(WWW_AUTHENTICATE, HeaderValue::from_str(v).ok().unwrap());
+        }
+        headers
+    }
+
+    /// Returns the value of the named param of an `Authorization` header.
+    fn param(value: &str, name: &str) -> Option<String> {
+        split_params(value.trim_start_matches("Digest "))
+            .iter()
+            .find_map(|p| {
+                let (k, v) = p.trim().split_once('=')?;
+                (k == name).then(|| unquote(v))
+            })
+    }
+
+    macro_rules! parse_tests {
+        ($($name:ident: $value:expr,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let (prov, exp): (&str, &[&str]) = $value;
+                let got: Vec<String> = parse_challenges(prov)
+                    .iter()
+                    .map(|c| match c {
+                        Challenge::Basic => "basic".to_string(),
+                        Challenge::Digest(d) => format!(
+                            "digest:{}:{}:{}:{}",
+                            d.realm, d.nonce, d.algorithm.name(), d.qop_auth
+                        ),
+                    })
+                    .collect();
+                assert_eq!(got, exp);
+            }
+        )*
+        }
+    }
+
+    parse_tests! {
+        parse_0: (r#"Basic realm="x""#, &["basic"]),
+        parse_1: (r#"Digest realm="a, b", qop="auth,auth-int", nonce="n1""#, &["digest:a, b:n1:MD5:true"]),
+        parse_2: (r#"Digest realm="r", nonce="n", algorithm=SHA-256, Basic realm="r""#, &["digest:r:n:SHA-256:false", "basic"]),
+        parse_3: (r#"Bearer realm="api", error="invalid_token""#, &[]),
+        parse_4: (r#"Digest realm="r", nonce="n", qop="auth-int""#, &[]),
+        parse_5: (r#"Digest realm="r""#, &[]),
+        parse_6: (r#"Newauth realm="apps", type=1, Basic realm="simple""#, &["basic"]),
+        parse_7: (r#"Digest realm="say \"hi\"", nonce="n""#, &["digest:say \"hi\":n:MD5:false"]),
+    }
+
+    #[test]
+    fn digest_rfc2617() {
+        // example from RFC 2617, section 3.5
+        let mut challenge = match parse_challenges(
+            r#"Digest realm="testrealm@host.com", qop="auth,auth-int", nonce="dcd98b7102dd2f0e8b11d0f600bfb0c093", opaque="5ccc069c403ebaf9f0171e9517f40e41""#,
+        )
+        .pop()
+        {
+            Some(Challenge::Digest(d)) => d,
+            _ => panic!("expected a digest challenge"),
+        };
+        let url = url("http://www.nowhere.org/dir/index.html");
+        let value = digest_response(
+            &mut challenge,
+            "Mufasa",
+            "Circle Of Life",
+            &Method::GET,
+            &url,
+        );
+        let cnonce = param(&value, "cnonce").unwrap_or_default();
+        let ha1 = DigestAlgorithm::Md5.hash("Mufasa:testrealm@host.com:Circle Of Life");
+        let ha2 = DigestAlgorithm::Md5.hash("GET:/dir/index.html");
+        let exp = DigestAlgorithm::Md5.hash(&format!(
+            "{}:dcd98b7102dd2f0e8b11d0f600bfb0c093:00000001:{}:auth:{}",
+            ha1, cnonce, ha2
+        ));
+        assert_eq!(param(&value, "response"), Some(exp));
+        assert_eq!(param(&value, "nc").as_deref(), Some("00000001"));
+        assert_eq!(param(&value, "uri").as_deref(), Some("/dir/index.html"));
+        assert_eq!(
+            param(&value, "opaque").as_deref(),
+            Some("5ccc069c403ebaf9f0171e9517f40e41")
+        );
+        // nonce count increments with each use
+        let value = digest_response(
+            &mut challenge,
+            "Mufasa",
+            "Circle Of Life",
+            &Method::GET,
+            &url,
+        );
+        assert_eq!(param(&value, "nc").as_deref(), Some("00000002"));
+    }
+
+    #[test]
+    fn digest_without_qop() {
+        let mut challenge = match parse_challenges(r#"Digest realm="r", nonce="abc""#).pop() {
+            Some(Challenge::Digest(d)) => d,
+            _ => panic!("expected a digest challenge"),
+        };
+        let value = digest_response(
+            &mut challenge,
+            "u",
+            "p",
+            &Method::GET,
+            &url("http://h/a?b=c"),
+        );
+        let ha1 = DigestAlgorithm::Md5.hash("u:r:p");
+        let ha2 = DigestAlgorithm::Md5.hash("GET:/a?b=c");
+        let exp = DigestAlgorithm::Md5.hash(&format!("{}:abc:{}", ha1, ha2));
+        assert_eq!(param(&value, "response"), Some(exp));
+        assert!(!value.contains("qop="));
+        assert_eq!(
+            DigestAlgorithm::Md5.hash(""),
+            "d41d8cd98f00b204e9800998ecf8427e"
+        );
+    }
+
+    #[test]
+    fn scope() {
+        let mut auth = HttpAuth::bearer("t0k3n");
+        auth.add_scope(&url("https://example.com/start"));
+        let header = auth.authorization(&Method::GET, &url("https://example.com/other"));
+        assert_eq!(
+            header.map(|h| h.to_str().unwrap().to_string()),
+            Some("Bearer t0k3n".to_string())
+        );
+        for u in [
+            "http://example.com/",
+            "https://example.com:8443/",
+            "https://sub.example.com/",
+            "https://example.org/",
+        ] {
+            assert!(auth.authorization(&Method::GET, &url(u)).is_none(), "{}", u);
+        }
+    }
+
+    #[test]
+    fn challenges() {
+        let target = url("https://example.com/");
+        let mut auth = HttpAuth::password("user", "pass", AuthMode::Any);
+        auth.add_scope(&target);
+        assert!(auth.authorization(&Method::GET, &target).is_none());
+
+        let headers = challenge_headers(&[r#"Basic realm="r""#]);
+        assert!(!auth.observe_challenge(&url("https://example.org/"), &headers, false));
+        assert!(auth.observe_challenge(&target, &headers, false));
+        assert!(!auth.observe_challenge(&target, &headers, true));
+        let header = auth.authorization(&Method::GET, &target);
+        assert_eq!(
+            header.map(|h| h.to_str().unwrap().to_string()),
+            Some(basic_response("user", "pass"))
+        );
+
+        let headers = challenge_headers(&[
+            r#"Basic realm="r""#,
+            r#"Digest realm="r", nonce="n", stale=true"#,
+        ]);
+        assert!(auth.observe_challenge(&target, &headers, true));
+        let header = auth.authorization(&Method::GET, &target).unwrap();
+        assert!(header
+            .to_str()
+            .unwrap()
+            .starts_with("Digest username=\"user\""));
+        assert!(header.is_sensitive());
+    }
+
+    #[test]
+    fn modes() {
+        let target = url("https://example.com/");
+        let mut auth = HttpAuth::password("user", "pass", AuthMode::Basic);
+        auth.add_scope(&target);
+        assert!(auth.authorization(&Method::GET, &target).is_some());
+        let digest = challenge_headers(&[r#"Digest realm="r", nonce="n""#]);
+        assert!(!auth.observe_challenge(&target, &digest, true));
+
+        let mut auth = HttpAuth::password("user", "pass", AuthMode::Digest);
+        auth.add_scope(&target);
+        let basic = challenge_headers(&[r#"Basic realm="r""#]);
+        assert!(!auth.observe_challenge(&target, &basic, false));
+        assert!(auth.observe_challenge(&target, &digest, false));
+
+        let mut auth = HttpAuth::bearer("t0k3n");
+        auth.add_scope(&target);
+        assert!(!auth.observe_challenge(&target, &basic, true));
+    }
+
+    #[test]
+    fn credentials() {
+        assert_eq!(
+            parse_credentials("user:pa:ss").ok(),
+            Some(("user".to_string(), "pa:ss".to_string()))
+        );
+        assert!(parse_credentials("user").is_err());
+    }
+}
@@ -1,24 +1,57 @@
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use log::{debug, info, trace, warn};
 use ratelimit::Ratelimiter;
-use reqwest::header::HeaderMap;
-use reqwest::{Client, Url};
+use reqwest::header::{
+    HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE, IF_MODIFIED_SINCE, IF_NONE_MATCH,
+    LOCATION, RETRY_AFTER,
+};
+use reqwest::redirect::Policy;
+use reqwest::{Client, ClientBuilder, Method, StatusCode, Url};
 use scraper::{node::Element, node::Node, Html};
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
 use std::fs;
 use std::io::Read;
 use std::sync::Arc;
-use tokio::sync::Semaphore;
+use tokio::sync::{OnceCell, OwnedSemaphorePermit, Semaphore};
 use tokio::time::{sleep, Duration};
 
-use crate::collections::{UrlDb, WordDb};
+use crate::collections::{CookieJar, UrlDb, Validators, WordDb};
 use crate::error::Error;
-use crate::extract::{ExtractOptions, Extractor};
+use crate::extract::{parse_content_type, DocKind, Document, ExtractOptions, Extractor, Feed};
 use crate::shutdown::Shutdown;
 use crate::utils;
 
-use super::SitePolicy;
+use super::{
+    asset_kind, asset_links, base_url, build_limiter, element_links, has_rel, interleave_by_host,
+    is_feed_link, is_script_link, is_stylesheet_link, is_text_asset, limiter_interval,
+    parse_retry_after, robots_url, sitemap_url, slow_limiter, AssetKind, CrawlBudget, Har,
+    HarEntry, HostLimit, HostLimits, HttpAuth, LinkSource, LoginForm, ProxySettings, RetryPolicy,
+    Robots, RobotsDb, SitePolicy, Sitemap, StatusClass, StatusRanges, TlsSettings, UrlNormalizer,
+    UrlScope, WarcArchive, WarcWriter, DEFAULT_ROBOTS_AGENT,
+};
+
+/// Limit on how deeply sitemap indexes may nest.
+const MAX_SITEMAP_NESTING: usize = 5;
+/// Limit on how many redirects are followed for a sitemap.
+const MAX_SITEMAP_REDIRECTS: usize = 5;
+/// Slowest pace the rate limiter is adaptively slowed down to when throttled.
+const MAX_THROTTLE_INTERVAL: Duration = Duration::from_secs(30);
+/// Default timeout for connecting to hosts.
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Default timeout for requests.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Default maximum number of redirects to follow per request.
+const DEFAULT_MAX_REDIRECTS: usize = 10;
+/// Limit on how many redirects are followed for robots.txt, as RFC 9309 asks.
+const MAX_ROBOTS_REDIRECTS: usize = 5;
+/// How long hosts are disallowed for when their robots.txt is unreachable,
+/// before it is fetched again.
+const ROBOTS_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+/// Limit on how many times a url out of retries is re-queued, across depths
+/// and resumes, before it is given up on.
+const MAX_REQUEUES: usize = 2;
 
 // light - ░
 // medium - ▒
@@ -66,12 +99,19 @@ const SPINNER_TICKS_STYLE_1: [&str; 35] = [
 /// Crawls websites, gathering urls from pages.
 pub struct Crawler {
     client: Client,
+    /// Client for robots.txt and sitemaps, following redirects of its own
+    /// accord rather than by the crawl's redirect policy.
+    discovery: Client,
     copts: CrawlOptions,
     eopts: ExtractOptions,
     urldb: UrlDb,
     worddb: WordDb,
     cur_depth: usize,
-    limiter: Ratelimiter,
+    limiter: Arc<Ratelimiter>,
+    hosts: HostLimits,
+    robots: RobotsDb,
+    /// Pages, bytes and time left to crawl; the clock starts once created.
+    budget: CrawlBudget,
     multiprog: MultiProgress,
     /// Listen for shutdown notifications.
     ///
@@ -89,9 +129,30 @@ impl Crawler {
         shutdown: Shutdown,
         multiprog: MultiProgress,
     ) -> Result<Self, Error> {
+        let client = Self::client_builder(&copts)?
+            .redirect(redirect_policy(
+                copts.seeds().to_vec(),
+                copts.site(),
+                copts.max_redirects(),
+            ))
+            .build()?;
+        // so a crawl's redirect policy can't keep robots.txt or sitemaps from
+        // being found, those redirects are followed by hand
+        let discovery = Self::client_builder(&copts)?
+            .redirect(Policy::none())
+            .build()?;
+        let mut crawler =
+            Self::new_with_client(client, copts, eopts, urldb, worddb, shutdown, multiprog)?;
+        crawler.discovery = discovery;
+        Ok(crawler)
+    }
+
+    /// Returns a `reqwest::ClientBuilder` configured by the provided options,
+    /// short of a redirect policy.
+    fn client_builder(copts: &CrawlOptions) -> Result<ClientBuilder, Error> {
         let builder = Client::builder()
-            .connect_timeout(Duration::from_secs(5))
-            .timeout(Duration::from_secs(10));
+            .connect_timeout(copts.connect_timeout())
+            .timeout(copts.timeout());
         let user_agent = copts.user_agent();
         let builder = if let Some(ua) = user_agent {
             builder.user_agent(ua)
@@ -104,11 +165,14 @@ impl Crawler {
         } else {
             builder
         };
-        let client = builder.build()?;
-        Self::new_with_client(client, copts, eopts, urldb, worddb, shutdown, multiprog)
+        let builder = copts.proxy().apply(builder)?;
+        let builder = copts.tls().apply(builder);
+        Ok(builder.cookie_provider(Arc::new(copts.cookie_jar())))
     }
 
-    /// Returns a new Crawler instance with the provided `reqwest::Client`.
+    /// Returns a new Crawler instance with the provided `reqwest::Client`;
+    /// the client is used for robots.txt and sitemaps as well, with redirects
+    /// its policy stops short of followed by hand.
     pub fn new_with_client(
         client: Client,
         copts: CrawlOptions,
@@ -118,17 +182,22 @@ impl Crawler {
         shutdown: Shutdown,
         multiprog: MultiProgress,
     ) -> Result<Self, Error> {
-        let tokens = if copts.requests_per_second() < 1 {
-            1
-        } else {
-            copts.requests_per_second()
-        };
-        let limiter = Ratelimiter::builder(tokens, Duration::from_secs(1))
-            .max_tokens(tokens)
-            .initial_available(tokens / 2)
-            .build()?;
-        let start_url = copts.url.clone();
+        let limiter = Arc::new(build_limiter(copts.requests_per_second())?);
+        let hosts = HostLimits::new(
+            copts.host_requests_per_second(),
+            copts.host_limit_concurrent(),
+        );
+        let seeds: Vec<Url> = copts
+            .seeds()
+            .iter()
+            .map(|s| match s.scheme() {
+                "file" => s.clone(),
+                _ => copts.normalizer().normalize(s),
+            })
+            .collect();
+        let budget = CrawlBudget::new(copts.max_pages(), copts.max_bytes(), copts.max_duration());
         let mut crawler = Self {
+            discovery: client.clone(),
             client,
             copts,
             eopts,
@@ -136,19 +205,48 @@ impl Crawler {
             worddb,
             cur_depth: 0,
             limiter,
+            hosts,
+            robots: RobotsDb::new(),
+            budget,
             shutdown,
             multiprog,
         };
-        crawler.urldb.cond_mark_unvisited(start_url.as_str());
+        for seed in seeds {
+            crawler.urldb.cond_mark_unvisited(seed.as_str());
+        }
         Ok(crawler)
     }
 
     /// Crawl urls up to a given limit, extracting words from documents;
     /// in web mode, links are extracted from web pages to spider;
-    /// in local mode, the directory structure is traversed to find documents;
-    /// returns the maximum depth reached upon success.
+    /// in archive mode, web pages are instead served from an archive;
+    /// in har mode, the entries of a har file are walked, without following links;
+    /// in local mode, the directory structure is traversed to find documents,
+    /// as it is for local seeds in web mode; returns the maximum depth reached upon success.
+    /// Crawling ends early once a budget is exhausted, leaving the remaining urls to
+    /// resume from.
     pub async fn crawl(&mut self) -> Result<usize, Error> {
+        if self.copts.mode == CrawlMode::Har {
+            self.crawl_har()?;
+            return Ok(self.cur_depth);
+        }
         let semaphore = Arc::new(Semaphore::new(self.copts.limit_concurrent()));
+        if self.copts.mode == CrawlMode::Web {
+            if let Some(login) = self.copts.login() {
+                login.login(&self.client).await?;
+            }
+        }
+        if self.cur_depth == 0 && self.copts.sitemaps() {
+            let mut spider = self.build_spider();
+            for seed in self.copts.seeds().iter().filter(|s| s.scheme() != "file") {
+                match self.copts.mode {
+                    CrawlMode::Web => spider.urls_from_sitemaps(seed).await,
+                    // archives are crawled offline, so only what was archived is read
+                    CrawlMode::Archive => spider.urls_from_archived_sitemaps(seed).await,
+                    CrawlMode::Local | CrawlMode::Har => {}
+                }
+            }
+        }
         while self.cur_depth < self.copts.depth() {
             // if staged urls are exhausted, populate stage and ratchet up depth
             if self.urldb.num_staged_urls() < 1 {
@@ -162,19 +260,39 @@ impl Crawler {
             info!("crawling at depth {}", self.cur_depth);
             let pb = self.new_stage_progress();
             let mut jhs = VecDeque::new();
-            for url_str in self.urldb.staged_urls_iter() {
+            let mut out_of_budget = false;
+            // cycle through hosts, so no single host is hit in a burst
+            for url_str in interleave_by_host(self.urldb.staged_urls_iter()) {
+                if self.budget.exhausted().is_some() {
+                    out_of_budget = true;
+                    break;
+                }
                 if self.observe_limit().await {
                     break;
                 }
 
                 let mut spider = self.build_spider();
                 let mut extractor = self.build_extractor();
+                let budget = self.budget.clone();
                 let sem = semaphore.clone();
                 let pbc = pb.clone();
                 let jh = tokio::spawn(async move {
+                    // wait on the host before taking a global slot, so urls
+                    // of a busy host don't hold up the urls of other hosts
+                    if !spider.reserve_host(&url_str).await {
+                        return;
+                    }
                     let permit = sem.acquire().await.unwrap();
-                    if let Some(doc) = spider.crawl_url(&url_str).await {
-                        extractor.words_from_doc(&doc);
+                    // left staged, to be crawled when resuming
+                    if !budget.reserve_page() {
+                        return;
+                    }
+                    match spider.crawl_url(&url_str).await {
+                        Some(doc) => {
+                            budget.add_bytes(doc.body().len() as u64);
+                            extractor.words_from_doc(&doc);
+                        }
+                        None => budget.release_page(),
                     }
                     pbc.inc(1);
                     pbc.set_message(format!("completed {}", url_str));
@@ -197,6 +315,19 @@ impl Crawler {
                 self.multiprog.remove(&pb);
                 break;
             }
+            if let Some(reason) = self.budget.exhausted() {
+                info!("{}; ending crawl early...", reason);
+                pb.abandon_with_message("out of budget...");
+                self.multiprog.remove(&pb);
+                break;
+            }
+            // pages reserved when the budget ran out were released since;
+            // carry on with the urls still staged at this depth
+            if out_of_budget {
+                pb.abandon();
+                self.multiprog.remove(&pb);
+                continue;
+            }
             self.cur_depth += 1;
             pb.finish();
             self.multiprog.remove(&pb);
@@ -205,9 +336,144 @@ impl Crawler {
         Ok(self.cur_depth)
     }
 
+    /// Walk the entries of the har file being crawled, extracting words from
+    /// the responses to requests matching the site policy and url scope; each
+    /// entry is recorded, as its url would be when crawled.
+    fn crawl_har(&mut self) -> Result<(), Error> {
+        let har = self
+            .copts
+            .har()
+            .ok_or(Error::GeneralError("no har file to crawl from".to_string()))?;
+        let normalizer = self.copts.normalizer();
+        let accepted = self.copts.accept_status();
+        let mut extractor = self.build_extractor();
+
+        info!("walking {} har entries", har.len());
+        let pb = self.multiprog.add(styled_progress(har.len() as u64));
+        pb.set_prefix("har entries");
+        for entry in har.entries() {
+            if self.shutdown.is_shutdown() {
+                pb.abandon_with_message("shutdown early...");
+                self.multiprog.remove(&pb);
+                return Ok(());
+            }
+            pb.inc(1);
+            let url = normalizer.normalize(&entry.url);
+            // repeated requests, or entries recorded before resuming
+            if !self.urldb.is_pending(url.as_str()) {
+                continue;
+            }
+            if !self.budget.reserve_page() {
+                if let Some(reason) = self.budget.exhausted() {
+                    info!("{}; ending crawl early...", reason);
+                }
+                pb.abandon_with_message("out of budget...");
+                self.multiprog.remove(&pb);
+                return Ok(());
+            }
+            match self.doc_from_har_entry(&url, entry, &accepted) {
+                Some(doc) => {
+                    self.budget.add_bytes(doc.body().len() as u64);
+                    extractor.words_from_doc(&doc);
+                }
+                None => self.budget.release_page(),
+            }
+            pb.set_message(format!("completed {}", url.as_str()));
+        }
+        // all entries are walked at once; there are no further depths
+        self.cur_depth = self.cur_depth.max(1);
+        pb.finish();
+        self.multiprog.remove(&pb);
+        Ok(())
+    }
+
+    /// Record the entry of a har file for the provided (normalized) url,
+    /// returning its response as a document if it is to be extracted from.
+    fn doc_from_har_entry(
+        &mut self,
+        url: &Url,
+        entry: &HarEntry,
+        accepted: &StatusRanges,
+    ) -> Option<Document> {
+        let site = self.copts.site();
+        if !site.matches_any(self.copts.seeds(), url) || !self.copts.scope().matches(url) {
+            debug!(
+                "har entry outside of site policy or scope: '{}', skipping...",
+                url.as_str()
+            );
+            self.urldb.mark_skipped(url.as_str());
+            return None;
+        }
+        let status = match entry.status {
+            Some(s) => s,
+            None => {
+                debug!("no response recorded for har entry: {}", url.as_str());
+                self.urldb.mark_errored(url.as_str());
+                return None;
+            }
+        };
+        self.urldb.set_status_code(url.as_str(), status.as_u16());
+        // redirect targets are recorded as entries of their own
+        if status.is_redirection() {
+            self.urldb.mark_skipped(url.as_str());
+            return None;
+        }
+        if !accepted.contains(status) {
+            debug!(
+                "{} response for har entry {}",
+                StatusClass::from_status(status),
+                url.as_str()
+            );
+            self.urldb.mark_errored(url.as_str());
+            return None;
+        }
+        let mime = entry
+            .headers
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| parse_content_type(v).0);
+        let asset = asset_kind(mime.as_deref(), &entry.url);
+        if (asset == Some(AssetKind::Js) && !self.copts.include_js())
+            || (asset == Some(AssetKind::Css) && !self.copts.include_css())
+        {
+            debug!(
+                "{} asset in har entry: '{}', skipping...",
+                asset.unwrap(),
+                url.as_str()
+            );
+            self.urldb.mark_skipped(url.as_str());
+            return None;
+        }
+        let (body, truncated) = match limit_size(
+            entry.body.clone(),
+            self.copts.max_doc_size(),
+            self.copts.truncate_oversized(),
+        ) {
+            Ok(b) => b,
+            Err(e) => {
+                debug!("skipping har entry {}: {}", url.as_str(), e);
+                self.urldb.mark_oversized(url.as_str());
+                return None;
+            }
+        };
+        if truncated {
+            self.urldb.mark_oversized(url.as_str());
+        } else {
+            self.urldb.mark_visited(url.as_str());
+        }
+        let mut doc = Document::with_headers(entry.headers.clone(), body);
+        doc.set_truncated(truncated);
+        doc.set_url(entry.url.clone());
+        Some(doc)
+    }
+
     // Observes configured rate limit and returns whether or not we've been
     // shutdown.
     async fn observe_limit(&mut self) -> bool {
+        // archives are crawled without making any requests
+        if self.copts.mode == CrawlMode::Archive {
+            return self.shutdown.is_shutdown();
+        }
         tokio::select! {
           _ = async {
             if let Err(dur) = self.limiter.try_wait() {
@@ -227,8 +493,11 @@ impl Crawler {
     fn build_spider(&self) -> Spider {
         Spider::new(
             self.client.clone(),
+            self.discovery.clone(),
             self.copts.clone(),
             self.urldb.clone(),
+            self.robots.clone(),
+            self.hosts.clone(),
             self.shutdown.clone(),
         )
     }
@@ -283,12 +552,66 @@ fn styled_spinner() -> ProgressBar {
     bar
 }
 
+/// Returns the body of a document already in hand, observing the maximum
+/// document size (0 for no limit) as when reading it from a response; returns
+/// the body and whether or not it was truncated.
+fn limit_size(mut body: Bytes, max_size: u64, truncate: bool) -> Result<(Bytes, bool), Error> {
+    if max_size == 0 || body.len() as u64 <= max_size {
+        return Ok((body, false));
+    }
+    if !truncate {
+        return Err(Error::OversizedError(max_size));
+    }
+    body.truncate(max_size as usize);
+    Ok((body, true))
+}
+
+/// Returns the headers of a conditional request for a document with the
+/// given validators.
+fn conditional_headers(validators: &Validators) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    let pairs = [
+        (IF_NONE_MATCH, &validators.etag),
+        (IF_MODIFIED_SINCE, &validators.last_modified),
+    ];
+    for (name, value) in pairs {
+        if let Some(v) = value.as_deref().and_then(|v| HeaderValue::from_str(v).ok()) {
+            headers.insert(name, v);
+        }
+    }
+    headers
+}
+
+/// Returns a redirect policy following at most the given number of
+/// redirects, re-checking the site policy against the seeds on every hop;
+/// redirects violating the site policy stop short, returning the redirect
+/// response itself.
+fn redirect_policy(seeds: Vec<Url>, site: SitePolicy, max_redirects: usize) -> Policy {
+    Policy::custom(move |attempt| {
+        if max_redirects == 0 || !site.matches_any(&seeds, attempt.url()) {
+            attempt.stop()
+        } else if attempt.previous().len() > max_redirects {
+            attempt.error(format!("too many redirects (max {})", max_redirects))
+        } else {
+            attempt.follow()
+        }
+    })
+}
+
 /// Crawls websites, gathering urls from pages.
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 struct Spider {
     client: Client,
+    /// Client for robots.txt and sitemaps.
+    discovery: Client,
     opts: CrawlOptions,
     urldb: UrlDb,
+    robots: RobotsDb,
+    /// Per-host limits shared with the crawler; slowed down to honor crawl delays.
+    hosts: HostLimits,
+    /// Permit for one concurrent request to a host, reserved ahead of
+    /// crawling a url of that host.
+    host_permit: Option<(String, Arc<OwnedSemaphorePermit>)>,
     /// Listen for shutdown notifications.
     ///
     /// A wrapper around the `broadcast::Receiver` to be paired with a sender.
@@ -297,16 +620,55 @@ struct Spider {
 
 impl Spider {
     /// Returns a new Spider instance with the provided `reqwest::Client`.
-    pub fn new(client: Client, opts: CrawlOptions, urldb: UrlDb, shutdown: Shutdown) -> Self {
+    pub fn new(
+        client: Client,
+        discovery: Client,
+        opts: CrawlOptions,
+        urldb: UrlDb,
+        robots: RobotsDb,
+        hosts: HostLimits,
+        shutdown: Shutdown,
+    ) -> Self {
         Self {
             opts,
             urldb,
+            robots,
+            hosts,
+            host_permit: None,
             client,
+            discovery,
             shutdown,
         }
     }
 
-    async fn crawl_url(&mut self, url_str: &str) -> Option<Bytes> {
+    /// Wait for a permit for one concurrent request to the host of the
+    /// provided url, held until the spider is dropped. Returns false if
+    /// shutdown while waiting.
+    async fn reserve_host(&mut self, url_str: &str) -> bool {
+        if self.opts.mode != CrawlMode::Web {
+            return true;
+        }
+        // anything not fetched over http is left to crawl_url
+        let url = match Url::parse(url_str) {
+            Ok(url) if url.scheme() == "http" || url.scheme() == "https" => url,
+            _ => return true,
+        };
+        let host = match self.hosts.get(&url) {
+            Ok(host) => host,
+            Err(_) => return true,
+        };
+        let permit = tokio::select! {
+            permit = host.acquire() => permit,
+            _ = self.shutdown.recv() => return false,
+        };
+        if let Some(permit) = permit {
+            let key = url.host_str().unwrap_or("").to_string();
+            self.host_permit = Some((key, Arc::new(permit)));
+        }
+        true
+    }
+
+    async fn crawl_url(&mut self, url_str: &str) -> Option<Document> {
         // give us a chance to receive graceful shutdown signal
         tokio::select! {
           _ = sleep(Duration::from_millis(u64::from(utils::num_between(20, 120)))) => {}
@@ -325,12 +687,16 @@ impl Spider {
         let url = result.unwrap();
 
         match self.opts.mode {
-            CrawlMode::Web => self.crawl_web(&url).await,
+            // local seeds may be mixed in with web seeds
+            CrawlMode::Web if url.scheme() == "file" => self.crawl_local(&url),
+            CrawlMode::Web | CrawlMode::Archive => self.crawl_web(&url).await,
             CrawlMode::Local => self.crawl_local(&url),
+            // entries are walked, rather than crawled
+            CrawlMode::Har => None,
         }
     }
 
-    fn crawl_local(&mut self, url: &Url) -> Option<Bytes> {
+    fn crawl_local(&mut self, url: &Url) -> Option<Document> {
         let path = url.to_file_path().unwrap();
         let display = path.display();
 
@@ -354,7 +720,7 @@ impl Spider {
         }
     }
 
-    fn handle_local_file(&mut self, url: &Url) -> Option<Bytes> {
+    fn handle_local_file(&mut self, url: &Url) -> Option<Document> {
         let path = url.to_file_path().unwrap();
         let display = path.display();
 
@@ -365,17 +731,36 @@ impl Spider {
             return None;
         }
 
-        let mut file = file_res.unwrap();
+        let file = file_res.unwrap();
+        let max_size = self.opts.max_doc_size();
+        // read at most one byte past the limit, to tell whether it was exceeded
+        let limit = if max_size > 0 {
+            max_size.saturating_add(1)
+        } else {
+            u64::MAX
+        };
         let mut buf = Vec::new();
-        match file.read_to_end(&mut buf) {
+        match file.take(limit).read_to_end(&mut buf) {
             Err(e) => {
                 self.urldb.mark_errored(url.as_str());
                 warn!("error reading file {}: {}", display, e);
                 None
             }
+            Ok(n) if max_size > 0 && n as u64 > max_size => {
+                self.urldb.mark_oversized(url.as_str());
+                if !self.opts.truncate_oversized() {
+                    debug!("file {} exceeds {} bytes, skipping...", display, max_size);
+                    return None;
+                }
+                debug!("file {} exceeds {} bytes, truncating...", display, max_size);
+                buf.truncate(max_size as usize);
+                let mut doc = Document::new(Bytes::from(buf));
+                doc.set_truncated(true);
+                Some(doc)
+            }
             Ok(_) => {
                 self.urldb.mark_visited(url.as_str());
-                Some(Bytes::from(buf))
+                Some(Document::new(Bytes::from(buf)))
             }
         }
     }
@@ -416,7 +801,7 @@ impl Spider {
         }
     }
 
-    async fn crawl_web(&mut self, url: &Url) -> Option<Bytes> {
+    async fn crawl_web(&mut self, url: &Url) -> Option<Document> {
         if !self.matches_site_policy(&url) {
             debug!(
                 "site policy '{}' violated for url: '{}', skipping...",
@@ -427,13 +812,60 @@ impl Spider {
             return None;
         }
 
+        if !self.matches_scope(url) {
+            debug!(
+                "url outside of include/exclude patterns: '{}', skipping...",
+                url.as_str()
+            );
+            self.urldb.mark_skipped(url.as_str());
+            return None;
+        }
+
+        // archives only hold what robots.txt allowed when they were written
+        if !self.opts.ignore_robots() && self.opts.mode == CrawlMode::Web {
+            match self.matches_robots(url).await {
+                Some(true) => {}
+                Some(false) => {
+                    debug!("disallowed by robots.txt: '{}', skipping...", url.as_str());
+                    self.urldb.mark_skipped(url.as_str());
+                    return None;
+                }
+                None => {
+                    self.requeue(url, "robots.txt unreachable");
+                    return None;
+                }
+            }
+        }
+
         trace!("visiting {}", url.as_str());
-        let document = self.doc_from_url(&url.as_str().to_string()).await;
+        let document = self.doc_from_url_logged_in(url).await;
         match document {
-            Ok(doc) => {
-                let doc_string = String::from_utf8_lossy(&doc).to_string();
-                self.urldb.mark_visited(url.as_str());
-                self.urls_from_doc(&url, &doc_string);
+            Ok((status, doc)) => {
+                self.urldb.set_status_code(url.as_str(), status.as_u16());
+                self.urldb.set_requeues(url.as_str(), 0);
+                if doc.truncated() {
+                    debug!("truncated oversized document: {}", url.as_str());
+                    self.urldb.mark_oversized(url.as_str());
+                } else {
+                    self.urldb.mark_visited(url.as_str());
+                }
+                self.urldb
+                    .set_validators(url.as_str(), Validators::from_headers(doc.headers()));
+                // links are relative to where the document was served from
+                let page_url = match doc.url() {
+                    Some(u) => u.clone(),
+                    None => url.clone(),
+                };
+                self.record_final_url(url, &page_url, status, doc.truncated());
+                match doc.kind() {
+                    DocKind::Html => self.urls_from_doc(&page_url, doc.text()),
+                    DocKind::Feed => self.urls_from_feed(&page_url, doc.text()),
+                    _ => {
+                        if let Some(kind) = asset_kind(doc.content_type().0.as_deref(), &page_url) {
+                            self.urls_from_asset(&page_url, doc.text(), kind);
+                        }
+                    }
+                }
                 Some(doc)
             }
             Err(e) => match e {
@@ -444,6 +876,59 @@ impl Spider {
                     //debug!("terminated while fetching: {}", url.as_str());
                     None
                 }
+                Error::RetryableError(status, _) => {
+                    self.urldb.set_status_code(url.as_str(), status.as_u16());
+                    // archived responses never change, so are not worth another try
+                    if self.opts.mode == CrawlMode::Archive {
+                        self.urldb.mark_errored(url.as_str());
+                        debug!("giving up on {} after: {}", url.as_str(), e);
+                    } else {
+                        self.requeue(url, &e);
+                    }
+                    None
+                }
+                Error::StatusError(status) => {
+                    self.urldb.set_status_code(url.as_str(), status.as_u16());
+                    self.urldb.mark_errored(url.as_str());
+                    debug!(
+                        "{} response for {}: {}",
+                        StatusClass::from_status(status),
+                        url.as_str(),
+                        e
+                    );
+                    None
+                }
+                Error::NotModifiedError => {
+                    // unchanged, so words and links were already extracted
+                    self.urldb
+                        .set_status_code(url.as_str(), StatusCode::NOT_MODIFIED.as_u16());
+                    self.urldb.mark_visited(url.as_str());
+                    debug!("unchanged since last visited: {}", url.as_str());
+                    None
+                }
+                Error::RedirectError(status, ref target) => {
+                    self.urldb.set_status_code(url.as_str(), status.as_u16());
+                    self.urldb.mark_skipped(url.as_str());
+                    // the target is crawled as a link would be, if allowed
+                    let target = self.opts.normalizer().normalize(target);
+                    if self.matches_site_policy(&target) {
+                        self.urldb.cond_mark_unvisited(target.as_str());
+                    } else {
+                        self.urldb.cond_mark_skipped(target.as_str());
+                    }
+                    debug!("skipping {}: {}", url.as_str(), e);
+                    None
+                }
+                Error::OversizedError(_) => {
+                    self.urldb.mark_oversized(url.as_str());
+                    debug!("skipping {}: {}", url.as_str(), e);
+                    None
+                }
+                Error::NotArchivedError(_) => {
+                    self.urldb.mark_skipped(url.as_str());
+                    debug!("skipping: {}", e);
+                    None
+                }
                 _ => {
                     self.urldb.mark_errored(url.as_str());
                     warn!("error fetching page {}: {}", url.as_str(), e);
@@ -453,58 +938,618 @@ impl Spider {
         }
     }
 
+    /// Record the url a document was finally served from, after redirects,
+    /// so that it is not fetched again.
+    fn record_final_url(
+        &mut self,
+        url: &Url,
+        final_url: &Url,
+        status: StatusCode,
+        truncated: bool,
+    ) {
+        let final_url = self.opts.normalizer().normalize(final_url);
+        if final_url.as_str() == url.as_str() {
+            return;
+        }
+        debug!("{} redirected to {}", url.as_str(), final_url.as_str());
+        self.urldb
+            .set_status_code(final_url.as_str(), status.as_u16());
+        if truncated {
+            self.urldb.mark_oversized(final_url.as_str());
+        } else {
+            self.urldb.mark_visited(final_url.as_str());
+        }
+    }
+
     /// Return whether or not the provided url matches the configured site policy.
     fn matches_site_policy(&self, url: &Url) -> bool {
-        return self.opts.site().matches_policy(&self.opts.url(), &url);
+        self.opts.site().matches_any(self.opts.seeds(), url)
     }
 
-    /// Get an html document from the provided url.
-    async fn doc_from_url(&mut self, url: &String) -> Result<Bytes, Error> {
-        tokio::select! {
-            response = self.client.get(url).send() => { Self::handle_response(response).await }
-            _ = self.shutdown.recv() => { Err(Error::EarlyTerminationError) }
+    /// Return whether or not the provided url matches the configured include
+    /// and exclude patterns.
+    fn matches_scope(&self, url: &Url) -> bool {
+        self.opts.scope().matches(url)
+    }
+
+    /// Return whether or not the provided url is allowed by the robots.txt of
+    /// its host; none while the robots.txt is unreachable.
+    async fn matches_robots(&mut self, url: &Url) -> Option<bool> {
+        let cell = self.robots_for(url).await;
+        match cell.get() {
+            Some(robots) if robots.is_unreachable() => None,
+            Some(robots) => Some(robots.is_allowed(url)),
+            None => Some(true),
         }
     }
 
-    async fn handle_response(
-        response: Result<reqwest::Response, reqwest::Error>,
-    ) -> Result<Bytes, Error> {
-        match response {
+    /// Re-queue the provided url for the next depth (or resume), as it could
+    /// not be fetched for now; once re-queued too many times, it is given up
+    /// on and marked errored.
+    fn requeue(&mut self, url: &Url, reason: impl fmt::Display) {
+        if self.urldb.add_requeue(url.as_str()) > MAX_REQUEUES {
+            self.urldb.set_requeues(url.as_str(), 0);
+            self.urldb.mark_errored(url.as_str());
+            debug!("giving up on {} after: {}", url.as_str(), reason);
+            return;
+        }
+        self.urldb.mark_unvisited(url.as_str());
+        debug!("re-queueing {} after: {}", url.as_str(), reason);
+    }
+
+    /// Return the cached robots.txt rules for the host of the provided url;
+    /// rules are fetched the first time a host is seen.
+    async fn robots_for(&self, url: &Url) -> Arc<OnceCell<Robots>> {
+        let cell = self.robots.cell(url);
+        let host = match self.hosts.get(url) {
+            Ok(h) => h,
             Err(e) => {
-                if e.is_status() {
-                    if let Some(status_code) = e.status() {
-                        match status_code.as_u16() {
-                            429 => {
-                                // breadcrumbs? https://github.com/rust-lang-nursery/rust-cookbook/pull/395/files
-                                debug!("wait and retry on 429 not implemented, skipping...");
+                warn!("error building limits for {}: {}", url.as_str(), e);
+                return cell;
+            }
+        };
+        cell.get_or_init(|| {
+            Self::fetch_robots(
+                self.discovery.clone(),
+                url.clone(),
+                self.opts.user_agent(),
+                host,
+                self.opts.retry_policy(),
+                self.opts.warc_writer(),
+                self.shutdown.clone(),
+            )
+        })
+        .await;
+        cell
+    }
+
+    /// Discover urls from the sitemaps of the provided url's host, as listed
+    /// in robots.txt or found at `/sitemap.xml`; nested sitemap indexes are
+    /// followed, and discovered urls are marked as unvisited.
+    async fn urls_from_sitemaps(&mut self, url: &Url) -> () {
+        let listed = match self.robots_for(url).await.get() {
+            Some(robots) => robots.sitemaps().cloned().collect(),
+            None => Vec::new(),
+        };
+        self.follow_sitemaps(url, listed).await;
+    }
+
+    /// Discover urls from the sitemaps of the provided url's host as
+    /// `urls_from_sitemaps` does, reading robots.txt and the sitemaps from
+    /// the archive being crawled rather than the web.
+    async fn urls_from_archived_sitemaps(&mut self, url: &Url) -> () {
+        let agent = self
+            .opts
+            .user_agent()
+            .unwrap_or(DEFAULT_ROBOTS_AGENT.to_string());
+        let listed = robots_url(url)
+            .and_then(|u| self.doc_from_archive(&u).ok())
+            .map(|(_, doc)| {
+                Robots::parse(doc.text(), &agent)
+                    .sitemaps()
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        self.follow_sitemaps(url, listed).await;
+    }
+
+    /// Follow the given sitemaps, along with the one at `/sitemap.xml` of the
+    /// provided url's host; nested sitemap indexes and redirects are followed,
+    /// and discovered urls are marked as unvisited.
+    async fn follow_sitemaps(&mut self, url: &Url, listed: Vec<String>) -> () {
+        // sitemaps are queued along with how deeply nested, and how many
+        // times redirected, they are
+        let mut queue: VecDeque<(String, usize, usize)> =
+            listed.into_iter().map(|s| (s, 0, 0)).collect();
+        if let Some(u) = sitemap_url(url) {
+            queue.push_back((u.as_str().to_string(), 0, 0));
+        }
+
+        let discovery = self.discovery.clone();
+        let mut seen = HashSet::new();
+        let mut found = 0;
+        while let Some((loc, nesting, redirects)) = queue.pop_front() {
+            if !seen.insert(loc.clone()) {
+                continue;
+            }
+            if nesting > MAX_SITEMAP_NESTING {
+                warn!("sitemaps nested too deeply, skipping: {}", loc);
+                continue;
+            }
+            let sitemap = match Url::parse(&loc) {
+                Ok(u) => u,
+                Err(e) => {
+                    debug!("not a sitemap url {}: {}", loc, e);
+                    continue;
+                }
+            };
+
+            trace!("fetching sitemap {}", loc);
+            let doc = match self.fetch_doc(&discovery, &sitemap).await {
+                Err(Error::EarlyTerminationError) => return,
+                Err(Error::RedirectError(_, target)) if redirects < MAX_SITEMAP_REDIRECTS => {
+                    trace!("sitemap {} redirected to {}", loc, target.as_str());
+                    queue.push_back((target.to_string(), nesting, redirects + 1));
+                    continue;
+                }
+                Err(e) => {
+                    debug!("error fetching sitemap {}: {}", loc, e);
+                    continue;
+                }
+                Ok((_, doc)) => doc,
+            };
+            match Sitemap::parse(doc.body()) {
+                Err(e) => debug!("error parsing sitemap {}: {}", loc, e),
+                Ok(Sitemap::Index(locs)) => {
+                    locs.into_iter()
+                        .for_each(|l| queue.push_back((l, nesting + 1, 0)));
+                }
+                Ok(Sitemap::UrlSet(locs)) => {
+                    for l in locs {
+                        if let Ok(u) = Url::parse(&l) {
+                            let u = self.opts.normalizer().normalize(&u);
+                            self.urldb.cond_mark_unvisited(u.as_str());
+                            found += 1;
+                        }
+                    }
+                }
+            }
+        }
+        info!("discovered {} urls from sitemaps", found);
+    }
+
+    /// Fetch and parse the robots.txt for the host of the provided url; a
+    /// missing file, or one redirected too many times, allows everything.
+    /// Redirects are followed up to a limit of their own, whatever the crawl's
+    /// redirect policy. Failed fetches are retried per the retry policy; an
+    /// unreachable file disallows everything for a while, to then be fetched
+    /// again. Responses are written to the WARC file, if any, so that
+    /// sitemaps listed may be found when crawling the archive.
+    async fn fetch_robots(
+        client: Client,
+        url: Url,
+        user_agent: Option<String>,
+        host: Arc<HostLimit>,
+        retry: RetryPolicy,
+        warc: Option<WarcWriter>,
+        mut shutdown: Shutdown,
+    ) -> Robots {
+        let mut target = match robots_url(&url) {
+            Some(u) => u,
+            None => return Robots::allow_all(),
+        };
+        let agent = user_agent.unwrap_or(DEFAULT_ROBOTS_AGENT.to_string());
+
+        let mut redirects = 0;
+        let mut attempt = 0;
+        let robots = loop {
+            trace!("fetching {}", target.as_str());
+            let response = tokio::select! {
+                response = client.get(target.clone()).send() => response,
+                _ = shutdown.recv() => return Robots::allow_all(),
+            };
+            let failure = match response {
+                Err(e) if e.is_redirect() => {
+                    debug!("too many redirects for {}, allowing all", target.as_str());
+                    break Robots::allow_all();
+                }
+                Err(e) => e.to_string(),
+                Ok(res) => {
+                    let status = res.status();
+                    let headers = res.headers().clone();
+                    let final_url = res.url().clone();
+                    let body = res.bytes().await;
+                    if let (Ok(b), Some(w)) = (&body, &warc) {
+                        let mut doc = Document::with_headers(headers.clone(), b.clone());
+                        doc.set_url(final_url.clone());
+                        if let Err(e) = w.write_response(&target, status, &doc) {
+                            warn!("error archiving {}: {}", target.as_str(), e);
+                        }
+                    }
+                    if status.is_redirection() {
+                        // redirects are followed by hand, up to a limit of their own
+                        let location = headers
+                            .get(LOCATION)
+                            .and_then(|v| v.to_str().ok())
+                            .and_then(|l| final_url.join(l).ok());
+                        match location {
+                            Some(l) if redirects < MAX_ROBOTS_REDIRECTS => {
+                                redirects += 1;
+                                target = l;
+                                continue;
                             }
                             _ => {
-                                debug!("unexpected status code: {}", status_code);
+                                debug!("unresolved redirect for {}, allowing all", target.as_str());
+                                break Robots::allow_all();
                             }
                         }
-                    } else {
-                        debug!("expected error with status code, but found none: {}", e);
                     }
-                } else {
-                    debug!("unexpected error: {}", e);
+                    if status.is_client_error() {
+                        debug!("no robots.txt found at {}", target.as_str());
+                        break Robots::allow_all();
+                    }
+                    match body {
+                        Ok(b) if status.is_success() => {
+                            break Robots::parse(&String::from_utf8_lossy(&b), &agent)
+                        }
+                        Ok(_) => format!("unexpected status code {}", status),
+                        Err(e) => e.to_string(),
+                    }
                 }
+            };
+
+            if attempt >= retry.max_retries() {
+                warn!(
+                    "error fetching {}, disallowing host for {:?}: {}",
+                    target.as_str(),
+                    ROBOTS_RETRY_INTERVAL,
+                    failure
+                );
+                break Robots::unreachable(ROBOTS_RETRY_INTERVAL);
+            }
+            attempt += 1;
+            let delay = retry.delay(attempt, None);
+            debug!(
+                "error fetching {}, retry {}/{} in {:?}: {}",
+                target.as_str(),
+                attempt,
+                retry.max_retries(),
+                delay,
+                failure
+            );
+            tokio::select! {
+                _ = sleep(delay) => {}
+                _ = shutdown.recv() => return Robots::allow_all(),
+            }
+        };
+
+        if let Some(delay) = robots.crawl_delay() {
+            Self::observe_crawl_delay(host.limiter(), delay);
+        }
+        robots
+    }
+
+    /// Slow the host's rate limiter down to one request per crawl delay, if it
+    /// is not already slower than that.
+    fn observe_crawl_delay(limiter: &Ratelimiter, delay: Duration) {
+        match slow_limiter(limiter, delay) {
+            Err(e) => warn!("failed observing crawl-delay of {:?}: {}", delay, e),
+            Ok(true) => info!("observing robots.txt crawl-delay of {:?}", delay),
+            Ok(false) => {}
+        }
+    }
+
+    /// Halve the pace of the host's rate limiter, as the host has started
+    /// throttling us.
+    fn observe_throttling(host: &HostLimit) {
+        let interval = limiter_interval(host.limiter())
+            .saturating_mul(2)
+            .min(MAX_THROTTLE_INTERVAL);
+        match slow_limiter(host.limiter(), interval) {
+            Err(e) => warn!("failed slowing down after being throttled: {}", e),
+            Ok(true) => info!("throttled; slowing down to one request per {:?}", interval),
+            Ok(false) => {}
+        }
+    }
+
+    /// Get an html document from the provided url, observing the limits of
+    /// its host; retryable failures are retried with backoff, according to
+    /// the configured retry policy; all responses fetched are written to the
+    /// configured WARC file, if any.
+    async fn doc_from_url(&mut self, url: &Url) -> Result<(StatusCode, Document), Error> {
+        let client = self.client.clone();
+        self.fetch_doc(&client, url).await
+    }
+
+    /// Get a document from the provided url with the given client, as with
+    /// `doc_from_url`.
+    async fn fetch_doc(
+        &mut self,
+        client: &Client,
+        url: &Url,
+    ) -> Result<(StatusCode, Document), Error> {
+        if self.opts.mode == CrawlMode::Archive {
+            return self.doc_from_archive(url);
+        }
+        let policy = self.opts.retry_policy();
+        let accepted = self.opts.accept_status();
+        let max_size = self.opts.max_doc_size();
+        let truncate = self.opts.truncate_oversized();
+        let auth = self.opts.auth();
+        let warc = self.opts.warc_writer();
+        // when refreshing, only download documents changed since last visited
+        let conditional = match self.opts.refresh() {
+            true => self
+                .urldb
+                .validators(url.as_str())
+                .map(|v| conditional_headers(&v))
+                .unwrap_or_default(),
+            false => HeaderMap::new(),
+        };
+        let host = self.hosts.get(url)?;
+        // the host's permit may have been reserved ahead of crawling
+        let reserved = matches!(
+            &self.host_permit,
+            Some((key, _)) if Some(key.as_str()) == url.host_str()
+        );
+        let _permit = match reserved {
+            true => None,
+            false => tokio::select! {
+                permit = host.acquire() => permit,
+                _ = self.shutdown.recv() => return Err(Error::EarlyTerminationError),
+            },
+        };
+        let mut attempt = 0;
+        loop {
+            tokio::select! {
+                _ = host.wait() => {}
+                _ = self.shutdown.recv() => return Err(Error::EarlyTerminationError),
+            }
+            let res = tokio::select! {
+                response = Self::send_get(client, &auth, url, &conditional) => { Self::handle_response(response, url, &accepted, max_size, truncate, warc.as_ref()).await }
+                _ = self.shutdown.recv() => { Err(Error::EarlyTerminationError) }
+            };
+
+            let (status, retry_after) = match res {
+                Err(Error::RetryableError(s, ra)) if attempt < policy.max_retries() => (s, ra),
+                _ => return res,
+            };
+            if RetryPolicy::is_throttled(status) {
+                Self::observe_throttling(&host);
+            }
+            attempt += 1;
+            let delay = policy.delay(attempt, retry_after);
+            debug!(
+                "status code {} for {}, retry {}/{} in {:?}",
+                status,
+                url.as_str(),
+                attempt,
+                policy.max_retries(),
+                delay
+            );
+            tokio::select! {
+                _ = sleep(delay) => {}
+                _ = self.shutdown.recv() => { return Err(Error::EarlyTerminationError) }
+            }
+        }
+    }
+
+    /// Send a GET request for the provided url, presenting credentials if
+    /// configured and in scope; an authentication challenge is answered once.
+    async fn send_get(
+        client: &Client,
+        auth: &Option<HttpAuth>,
+        url: &Url,
+        headers: &HeaderMap,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        let get = |url: &Url, authorization: Option<HeaderValue>| {
+            let mut request = client.get(url.clone()).headers(headers.clone());
+            if let Some(v) = authorization {
+                request = request.header(AUTHORIZATION, v);
+            }
+            request.send()
+        };
+        let auth = match auth {
+            Some(a) => a,
+            None => return get(url, None).await,
+        };
+        let authorization = auth.authorization(&Method::GET, url);
+        let response = get(url, authorization.clone()).await?;
+        // the challenge comes from wherever redirects led, and credentials
+        // are not carried across origins
+        let challenged = response.url().clone();
+        let sent = authorization.is_some() && challenged.origin() == url.origin();
+        if response.status() != StatusCode::UNAUTHORIZED
+            || !auth.observe_challenge(&challenged, response.headers(), sent)
+        {
+            return Ok(response);
+        }
+        debug!(
+            "answering authentication challenge for {}",
+            challenged.as_str()
+        );
+        get(&challenged, auth.authorization(&Method::GET, &challenged)).await
+    }
+
+    /// Get a document from the provided url, as with `doc_from_url`; if a
+    /// form login is configured and the document shows that the session was
+    /// logged out, log in again and refetch the document once.
+    async fn doc_from_url_logged_in(&mut self, url: &Url) -> Result<(StatusCode, Document), Error> {
+        let login = match self.opts.login() {
+            Some(l) if self.opts.mode == CrawlMode::Web => l,
+            _ => return self.doc_from_url(url).await,
+        };
+        let seen = login.logins();
+        let res = self.doc_from_url(url).await;
+        match &res {
+            Ok((_, doc)) if doc.kind() != DocKind::Binary && login.is_logged_out(doc.text()) => {
+                warn!(
+                    "logged out while fetching {}; logging in again",
+                    url.as_str()
+                );
+                login.relogin(&self.client, seen).await?;
+                let (status, doc) = self.doc_from_url(url).await?;
+                if doc.kind() != DocKind::Binary && login.is_logged_out(doc.text()) {
+                    return Err(Error::LoginError(format!(
+                        "still logged out after logging in again; fetching {}",
+                        url.as_str()
+                    )));
+                }
+                Ok((status, doc))
+            }
+            _ => res,
+        }
+    }
+
+    /// Classify the response to a request for the provided url by status
+    /// code, returning the body only if the status code is accepted; bodies
+    /// exceeding the maximum size are either truncated or rejected. Whatever
+    /// the status code, responses are written to the WARC file, if any, so
+    /// that they may be extracted from with other options later.
+    async fn handle_response(
+        response: Result<reqwest::Response, reqwest::Error>,
+        url: &Url,
+        accepted: &StatusRanges,
+        max_size: u64,
+        truncate: bool,
+        warc: Option<&WarcWriter>,
+    ) -> Result<(StatusCode, Document), Error> {
+        match response {
+            Err(e) => {
+                debug!("unexpected error: {}", e);
                 Err(Error::RequestError(e))
             }
             Ok(res) => {
-                let r = res.bytes().await;
+                let status = res.status();
+                // bodies of unaccepted responses are only read to be archived
+                let checked = match (
+                    Self::check_status(status, res.headers(), res.url(), accepted),
+                    warc,
+                ) {
+                    (Err(e), None) => return Err(e),
+                    (c, _) => c,
+                };
+                let headers = res.headers().clone();
+                let final_url = res.url().clone();
+                let r = Self::read_body(res, max_size, truncate).await;
                 match r {
                     Err(e) => {
                         debug!("error reading request response: {}", e);
-                        Err(Error::RequestError(e))
+                        checked?;
+                        Err(e)
+                    }
+                    Ok((ress, truncated)) => {
+                        let mut doc = Document::with_headers(headers, ress);
+                        doc.set_truncated(truncated);
+                        doc.set_url(final_url);
+                        if let Some(w) = warc {
+                            if let Err(e) = w.write_response(url, status, &doc) {
+                                warn!("error archiving {}: {}", url.as_str(), e);
+                            }
+                        }
+                        checked?;
+                        Ok((status, doc))
                     }
-                    Ok(ress) => Ok(ress),
                 }
             }
         }
     }
 
+    /// Classify the status code of a response served from the provided url,
+    /// returning an error unless it is accepted for extraction.
+    fn check_status(
+        status: StatusCode,
+        headers: &HeaderMap,
+        url: &Url,
+        accepted: &StatusRanges,
+    ) -> Result<(), Error> {
+        // accepted statuses are extracted from, rather than retried
+        if RetryPolicy::is_retryable(status) && !accepted.contains(status) {
+            let retry_after = headers
+                .get(RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(parse_retry_after);
+            return Err(Error::RetryableError(status, retry_after));
+        }
+        if status == StatusCode::NOT_MODIFIED {
+            return Err(Error::NotModifiedError);
+        }
+        // redirects stop short when they violate the redirect policy
+        if status.is_redirection() {
+            let location = headers
+                .get(LOCATION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|l| url.join(l).ok());
+            if let Some(target) = location {
+                return Err(Error::RedirectError(status, target));
+            }
+        }
+        if !accepted.contains(status) {
+            return Err(Error::StatusError(status));
+        }
+        Ok(())
+    }
+
+    /// Get a document from the provided url as archived, classifying it as
+    /// if it were served; the maximum document size is observed as well.
+    fn doc_from_archive(&self, url: &Url) -> Result<(StatusCode, Document), Error> {
+        let archive = self
+            .opts
+            .archive()
+            .ok_or(Error::GeneralError("no archive to crawl from".to_string()))?;
+        let res = archive
+            .get(url)
+            .ok_or(Error::NotArchivedError(url.clone()))?;
+        Self::check_status(
+            res.status,
+            &res.headers,
+            &res.url,
+            &self.opts.accept_status(),
+        )?;
+
+        let (body, truncated) = limit_size(
+            res.body.clone(),
+            self.opts.max_doc_size(),
+            self.opts.truncate_oversized(),
+        )?;
+        let mut doc = Document::with_headers(res.headers.clone(), body);
+        doc.set_truncated(truncated || res.truncated);
+        doc.set_url(res.url.clone());
+        Ok((res.status, doc))
+    }
+
+    /// Stream the body of the response, observing the maximum document size
+    /// (0 for no limit); returns the body and whether or not it was truncated.
+    async fn read_body(
+        mut res: reqwest::Response,
+        max_size: u64,
+        truncate: bool,
+    ) -> Result<(Bytes, bool), Error> {
+        if max_size > 0 && !truncate {
+            if let Some(len) = res.content_length() {
+                if len > max_size {
+                    return Err(Error::OversizedError(max_size));
+                }
+            }
+        }
+        let limit = usize::try_from(max_size).unwrap_or(usize::MAX);
+        let mut buf = BytesMut::new();
+        while let Some(chunk) = res.chunk().await? {
+            if max_size > 0 && buf.len() + chunk.len() > limit {
+                if !truncate {
+                    return Err(Error::OversizedError(max_size));
+                }
+                buf.extend_from_slice(&chunk[..limit - buf.len()]);
+                return Ok((buf.freeze(), true));
+            }
+            buf.extend_from_slice(&chunk);
+        }
+        Ok((buf.freeze(), false))
+    }
+
     /// Extract urls from an html document.
-    fn urls_from_doc(&mut self, url: &Url, document: &String) -> () {
+    fn urls_from_doc(&mut self, url: &Url, document: &str) -> () {
         let doc = Html::parse_document(&document);
         // breadcrumbs for using selector to extract urls from elements...
         //let link_selector = Selector::parse(r#"a[href^="http"]"#).unwrap();
@@ -514,19 +1559,97 @@ impl Spider {
         //        .entry(String::from(elem.value().attr("href").unwrap()))
         //        .or_insert(false);
         //}
+        let normalizer = self.opts.normalizer();
+        let base = base_url(&doc, url);
         for d in doc.root_element().descendants() {
+            // inline styles and scripts
+            if let Node::Text(text) = d.value() {
+                let parent = d
+                    .parent()
+                    .and_then(|p| p.value().as_element().map(|e| e.name()));
+                match parent {
+                    Some("style") => self.urls_from_asset(&base, text, AssetKind::Css),
+                    Some("script") => self.urls_from_asset(&base, text, AssetKind::Js),
+                    _ => {}
+                }
+            }
             if let Node::Element(elem) = d.value() {
-                if let Some(href) = elem.attr("href") {
-                    let final_url = Self::url_from_href(url, href);
+                for (source, href) in element_links(elem) {
+                    let final_url = Self::url_from_href(&base, &href)
+                        .and_then(|u| Ok(normalizer.normalize(&Url::parse(&u)?)));
                     match final_url {
                         Err(_e) => continue, // just skip href;
-                        Ok(u) => self.conditional_insert_url(&u, elem),
+                        Ok(u) if Self::is_canonical(elem) => {
+                            self.observe_canonical(url, &u, &normalizer)
+                        }
+                        Ok(u) => self.conditional_insert_url(u.as_str(), source, elem),
                     }
                 }
             }
         }
     }
 
+    /// Returns whether or not the given element is a `<link rel="canonical">`.
+    fn is_canonical(elem: &Element) -> bool {
+        elem.name() == "link" && has_rel(elem, "canonical")
+    }
+
+    /// Mark the canonical url of a page as visited, as its content was just
+    /// processed under the page's url; only honored if the canonical url
+    /// matches the configured site policy.
+    fn observe_canonical(&mut self, page: &Url, canonical: &Url, normalizer: &UrlNormalizer) {
+        if *canonical == normalizer.normalize(page) || !self.matches_site_policy(canonical) {
+            return;
+        }
+        trace!(
+            "page {} is a duplicate of canonical {}",
+            page.as_str(),
+            canonical.as_str()
+        );
+        self.urldb.mark_visited(canonical.as_str());
+    }
+
+    /// Extract urls from a css or js asset, if configuration dictates to
+    /// include that kind of asset; references to binary assets are ignored.
+    fn urls_from_asset(&mut self, url: &Url, text: &str, kind: AssetKind) {
+        let include = match kind {
+            AssetKind::Css => self.opts.include_css(),
+            AssetKind::Js => self.opts.include_js(),
+        };
+        if !include {
+            return;
+        }
+        let normalizer = self.opts.normalizer();
+        for href in asset_links(text, kind) {
+            let final_url = Self::url_from_href(url, &href)
+                .and_then(|u| Ok(normalizer.normalize(&Url::parse(&u)?)));
+            if let Ok(u) = final_url {
+                if is_text_asset(&u) {
+                    self.urldb.cond_mark_unvisited(u.as_str());
+                }
+            }
+        }
+    }
+
+    /// Extract the urls of items from an RSS or Atom feed.
+    fn urls_from_feed(&mut self, url: &Url, text: &str) {
+        let feed = match Feed::parse(text) {
+            Ok(f) => f,
+            Err(e) => {
+                debug!("unable to parse feed {}: {}", url.as_str(), e);
+                return;
+            }
+        };
+        let normalizer = self.opts.normalizer();
+        for href in feed.links() {
+            let final_url = Self::url_from_href(url, href)
+                .and_then(|u| Ok(normalizer.normalize(&Url::parse(&u)?)));
+            if let Ok(u) = final_url {
+                self.urldb.cond_mark_unvisited(u.as_str());
+            }
+        }
+    }
+
     /// Based on a page's original url, return a url based on the given href extracted from the
     /// page.
     fn url_from_href(url: &Url, href: &str) -> Result<String, Error> {
@@ -557,33 +1680,46 @@ impl Spider {
     }
 
     /// Conditionally save the given url if it adheres to configured options,
-    /// determined by the given element; given element is intended to be the
-    /// element from which  the url was extracted from.
-    fn conditional_insert_url(&mut self, url: &str, elem: &Element) -> () {
+    /// determined by the source of the link and the given element; given
+    /// element is intended to be the element from which  the url was extracted from.
+    fn conditional_insert_url(&mut self, url: &str, source: LinkSource, elem: &Element) {
         if url != "" {
-            match elem.name() {
-                "link" => {
-                    if self.opts.include_css() && elem.attr("rel").unwrap() == "stylesheet" {
+            match source {
+                LinkSource::Link => {
+                    if self.follows_link_elem(elem) {
                         self.urldb.cond_mark_unvisited(url);
                     }
-                    if self.opts.include_js() && elem.attr("as").unwrap() == "script" {
+                }
+                LinkSource::Script => {
+                    if self.opts.include_js() {
                         self.urldb.cond_mark_unvisited(url);
                     }
                 }
-                "a" => {
-                    self.urldb.cond_mark_unvisited(url);
+                s => {
+                    if self.opts.follow_links().contains(&s) {
+                        self.urldb.cond_mark_unvisited(url);
+                    }
                 }
-                _ => {}
             }
         }
     }
+
+    /// Returns whether or not the given `<link>` element should be followed,
+    /// based on its relations and the configured options; feeds are always
+    /// followed.
+    fn follows_link_elem(&self, elem: &Element) -> bool {
+        is_feed_link(elem)
+            || (self.opts.include_css() && is_stylesheet_link(elem))
+            || (self.opts.include_js() && is_script_link(elem))
+            || self.opts.follow_rel().iter().any(|r| has_rel(elem, r))
+    }
 }
 
 /// Options used when crawling and building wordlists.
 #[derive(Debug, Clone)]
 pub struct CrawlOptions {
-    /// Url to start crawling from.
-    url: Url,
+    /// Urls to start crawling from, each anchoring the site policy; never empty.
+    seeds: Vec<Url>,
     /// Limit the depth of crawling urls.
     depth: usize,
     /// Include javascript from html pages.
@@ -596,12 +1732,66 @@ pub struct CrawlOptions {
     req_per_sec: u64,
     /// Maximum number of concurrent requests.
     limit_concurrent: usize,
+    /// Upper limit of requests per second to any single host.
+    host_req_per_sec: u64,
+    /// Maximum number of concurrent requests to any single host.
+    host_limit_concurrent: usize,
     /// Crawl mode.
     mode: CrawlMode,
     /// Custom User Agent string.
     user_agent: Option<String>,
     /// Custom HTTP headers.
     headers: Option<HeaderMap>,
+    /// Ignore robots.txt rules while crawling.
+    ignore_robots: bool,
+    /// Seed crawling with urls discovered from sitemaps.
+    sitemaps: bool,
+    /// Policy for retrying throttled or failed requests.
+    retry: RetryPolicy,
+    /// Status codes of responses to accept for extraction.
+    accept_status: StatusRanges,
+    /// Maximum size in bytes of a document; 0 for no limit.
+    max_doc_size: u64,
+    /// Normalization applied to discovered urls.
+    normalizer: UrlNormalizer,
+    /// Include and exclude patterns for urls to crawl.
+    scope: UrlScope,
+    /// Sources of links to follow, besides scripts and `<link>` elements.
+    follow_links: Vec<LinkSource>,
+    /// Relations of `<link>` elements to follow, besides stylesheets and scripts.
+    follow_rel: Vec<String>,
+    /// Truncate documents exceeding the maximum size, rather than skipping them.
+    truncate_oversized: bool,
+    /// Proxies to route requests through.
+    proxy: ProxySettings,
+    /// TLS configuration for requests.
+    tls: TlsSettings,
+    /// Cookies kept across requests.
+    cookie_jar: CookieJar,
+    /// Form login performed before crawling, and again once logged out.
+    login: Option<LoginForm>,
+    /// HTTP authentication for requests within its scope.
+    auth: Option<HttpAuth>,
+    /// Only download documents changed since last visited, per their validators.
+    refresh: bool,
+    /// Timeout for connecting to hosts.
+    connect_timeout: Duration,
+    /// Timeout for requests, from connecting until the body is read.
+    timeout: Duration,
+    /// Maximum number of redirects to follow per request; 0 to follow none.
+    max_redirects: usize,
+    /// Archive crawled in place of the web, in archive mode.
+    archive: Option<WarcArchive>,
+    /// WARC file fetched responses are written to.
+    warc_writer: Option<WarcWriter>,
+    /// Har file whose entries are walked, in har mode.
+    har: Option<Har>,
+    /// Maximum number of pages to extract from in a crawl; 0 for no limit.
+    max_pages: usize,
+    /// Maximum number of bytes of documents to read in a crawl; 0 for no limit.
+    max_bytes: u64,
+    /// Maximum duration of a crawl; none for no limit.
+    max_duration: Option<Duration>,
 }
 
 impl CrawlOptions {
@@ -619,22 +1809,68 @@ impl CrawlOptions {
         headers: Option<HeaderMap>,
     ) -> Self {
         Self {
-            url: url.clone(),
+            seeds: vec![url.clone()],
             depth,
             include_js,
             include_css,
             site,
             req_per_sec,
             limit_concurrent,
+            host_req_per_sec: req_per_sec,
+            host_limit_concurrent: limit_concurrent,
             mode,
             user_agent,
             headers,
+            ignore_robots: false,
+            sitemaps: false,
+            retry: RetryPolicy::default(),
+            accept_status: StatusRanges::default(),
+            max_doc_size: 0,
+            normalizer: UrlNormalizer::default(),
+            scope: UrlScope::default(),
+            follow_links: vec![
+                LinkSource::Anchor,
+                LinkSource::Frame,
+                LinkSource::Form,
+                LinkSource::Refresh,
+            ],
+            follow_rel: vec!["next".to_string(), "prev".to_string()],
+            truncate_oversized: false,
+            proxy: ProxySettings::default(),
+            tls: TlsSettings::default(),
+            cookie_jar: CookieJar::new(),
+            login: None,
+            auth: None,
+            refresh: false,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            timeout: DEFAULT_TIMEOUT,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            archive: None,
+            warc_writer: None,
+            har: None,
+            max_pages: 0,
+            max_bytes: 0,
+            max_duration: None,
         }
     }
 
-    /// Returns the url where crawling initiated from.
+    /// Returns the url where crawling initiated from; the first of the seeds.
     pub fn url(&self) -> Url {
-        self.url.clone()
+        self.seeds[0].clone()
+    }
+
+    /// Returns the urls where crawling initiated from.
+    pub fn seeds(&self) -> &[Url] {
+        &self.seeds
+    }
+
+    /// Set the urls where crawling initiates from, replacing the url given
+    /// on construction; urls are discovered per the site policy of any seed,
+    /// so seeds of different sites are crawled alike. Ignored if empty.
+    pub fn set_seeds(&mut self, seeds: Vec<Url>) {
+        if !seeds.is_empty() {
+            self.seeds = seeds;
+        }
     }
 
     /// Returns the url search depth used for crawling.
@@ -662,6 +1898,26 @@ impl CrawlOptions {
         self.limit_concurrent
     }
 
+    /// Returns number of configured requests per second to any single host.
+    pub fn host_requests_per_second(&self) -> u64 {
+        self.host_req_per_sec
+    }
+
+    /// Set the upper limit of requests per second to any single host.
+    pub fn set_host_requests_per_second(&mut self, req_per_sec: u64) {
+        self.host_req_per_sec = req_per_sec;
+    }
+
+    /// Returns maximum number of concurrent requests allowed to any single host.
+    pub fn host_limit_concurrent(&self) -> usize {
+        self.host_limit_concurrent
+    }
+
+    /// Set the maximum number of concurrent requests to any single host.
+    pub fn set_host_limit_concurrent(&mut self, limit_concurrent: usize) {
+        self.host_limit_concurrent = limit_concurrent;
+    }
+
     /// Returns the configured site policy for visiting discovered URLs.
     pub fn site(&self) -> SitePolicy {
         self.site
@@ -676,12 +1932,280 @@ impl CrawlOptions {
     pub fn headers(&self) -> Option<HeaderMap> {
         self.headers.clone()
     }
+
+    /// Returns whether or not configuration dictates to ignore robots.txt.
+    pub fn ignore_robots(&self) -> bool {
+        self.ignore_robots
+    }
+
+    /// Set whether or not to ignore robots.txt rules while crawling.
+    pub fn set_ignore_robots(&mut self, ignore: bool) {
+        self.ignore_robots = ignore;
+    }
+
+    /// Returns whether or not configuration dictates to seed urls from sitemaps.
+    pub fn sitemaps(&self) -> bool {
+        self.sitemaps
+    }
+
+    /// Set whether or not to seed urls from sitemaps before crawling.
+    pub fn set_sitemaps(&mut self, sitemaps: bool) {
+        self.sitemaps = sitemaps;
+    }
+
+    /// Returns the configured policy for retrying requests.
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry
+    }
+
+    /// Set the policy for retrying requests.
+    pub fn set_retry_policy(&mut self, retry: RetryPolicy) {
+        self.retry = retry;
+    }
+
+    /// Returns the status codes of responses accepted for extraction.
+    pub fn accept_status(&self) -> StatusRanges {
+        self.accept_status.clone()
+    }
+
+    /// Set the status codes of responses accepted for extraction.
+    pub fn set_accept_status(&mut self, accept_status: StatusRanges) {
+        self.accept_status = accept_status;
+    }
+
+    /// Returns the maximum size in bytes of a document; 0 for no limit.
+    pub fn max_doc_size(&self) -> u64 {
+        self.max_doc_size
+    }
+
+    /// Set the maximum size in bytes of a document; 0 for no limit.
+    pub fn set_max_doc_size(&mut self, max_doc_size: u64) {
+        self.max_doc_size = max_doc_size;
+    }
+
+    /// Returns the normalization applied to discovered urls.
+    pub fn normalizer(&self) -> UrlNormalizer {
+        self.normalizer.clone()
+    }
+
+    /// Set the normalization applied to discovered urls.
+    pub fn set_normalizer(&mut self, normalizer: UrlNormalizer) {
+        self.normalizer = normalizer;
+    }
+
+    /// Returns the include and exclude patterns for urls to crawl.
+    pub fn scope(&self) -> &UrlScope {
+        &self.scope
+    }
+
+    /// Set the include and exclude patterns for urls to crawl.
+    pub fn set_scope(&mut self, scope: UrlScope) {
+        self.scope = scope;
+    }
+
+    /// Returns the sources of links to follow, besides scripts and `<link>` elements.
+    pub fn follow_links(&self) -> &[LinkSource] {
+        &self.follow_links
+    }
+
+    /// Set the sources of links to follow, besides scripts and `<link>` elements.
+    pub fn set_follow_links(&mut self, follow_links: Vec<LinkSource>) {
+        self.follow_links = follow_links;
+    }
+
+    /// Returns the relations of `<link>` elements to follow, besides
+    /// stylesheets and scripts.
+    pub fn follow_rel(&self) -> &[String] {
+        &self.follow_rel
+    }
+
+    /// Set the relations of `<link>` elements to follow, besides stylesheets
+    /// and scripts.
+    pub fn set_follow_rel(&mut self, follow_rel: Vec<String>) {
+        self.follow_rel = follow_rel
+            .into_iter()
+            .map(|r| r.trim().to_lowercase())
+            .filter(|r| !r.is_empty())
+            .collect();
+    }
+
+    /// Returns the proxies to route requests through.
+    pub fn proxy(&self) -> &ProxySettings {
+        &self.proxy
+    }
+
+    /// Set the proxies to route requests through.
+    pub fn set_proxy(&mut self, proxy: ProxySettings) {
+        self.proxy = proxy;
+    }
+
+    /// Returns a handle to the cookies kept across requests.
+    pub fn cookie_jar(&self) -> CookieJar {
+        self.cookie_jar.clone()
+    }
+
+    /// Set the cookies kept across requests; the given jar is shared with
+    /// the crawler, so it reflects cookies set while crawling.
+    pub fn set_cookie_jar(&mut self, cookie_jar: CookieJar) {
+        self.cookie_jar = cookie_jar;
+    }
+
+    /// Returns the form login performed before crawling, if any.
+    pub fn login(&self) -> Option<LoginForm> {
+        self.login.clone()
+    }
+
+    /// Set the form login performed before crawling, and again whenever
+    /// a crawled page shows that the session was logged out.
+    pub fn set_login(&mut self, login: Option<LoginForm>) {
+        self.login = login;
+    }
+
+    /// Returns whether or not documents are only downloaded when changed
+    /// since last visited.
+    pub fn refresh(&self) -> bool {
+        self.refresh
+    }
+
+    /// Set whether or not to make conditional requests for documents with
+    /// validators (`ETag`/`Last-Modified`) recorded from a previous visit,
+    /// only downloading them when changed.
+    pub fn set_refresh(&mut self, refresh: bool) {
+        self.refresh = refresh;
+    }
+
+    /// Returns the timeout for connecting to hosts.
+    pub fn connect_timeout(&self) -> Duration {
+        self.connect_timeout
+    }
+
+    /// Set the timeout for connecting to hosts.
+    pub fn set_connect_timeout(&mut self, connect_timeout: Duration) {
+        self.connect_timeout = connect_timeout;
+    }
+
+    /// Returns the timeout for requests.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Set the timeout for requests, from connecting until the body is read.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Returns the maximum number of redirects to follow per request.
+    pub fn max_redirects(&self) -> usize {
+        self.max_redirects
+    }
+
+    /// Set the maximum number of redirects to follow per request; with 0,
+    /// redirect targets are instead queued like any other discovered link.
+    pub fn set_max_redirects(&mut self, max_redirects: usize) {
+        self.max_redirects = max_redirects;
+    }
+
+    /// Returns the HTTP authentication for requests, if any.
+    pub fn auth(&self) -> Option<HttpAuth> {
+        self.auth.clone()
+    }
+
+    /// Set the HTTP authentication for requests; credentials are only
+    /// presented to urls within its scope.
+    pub fn set_auth(&mut self, auth: Option<HttpAuth>) {
+        self.auth = auth;
+    }
+
+    /// Returns the archive crawled in place of the web, if any.
+    pub fn archive(&self) -> Option<WarcArchive> {
+        self.archive.clone()
+    }
+
+    /// Set the archive crawled in place of the web, in archive mode.
+    pub fn set_archive(&mut self, archive: Option<WarcArchive>) {
+        self.archive = archive;
+    }
+
+    /// Returns the WARC file fetched responses are written to, if any.
+    pub fn warc_writer(&self) -> Option<WarcWriter> {
+        self.warc_writer.clone()
+    }
+
+    /// Set the WARC file fetched responses are written to.
+    pub fn set_warc_writer(&mut self, warc_writer: Option<WarcWriter>) {
+        self.warc_writer = warc_writer;
+    }
+
+    /// Returns the har file whose entries are walked, if any.
+    pub fn har(&self) -> Option<Har> {
+        self.har.clone()
+    }
+
+    /// Set the har file whose entries are walked, in har mode.
+    pub fn set_har(&mut self, har: Option<Har>) {
+        self.har = har;
+    }
+
+    /// Returns the maximum number of pages to extract from in a crawl.
+    pub fn max_pages(&self) -> usize {
+        self.max_pages
+    }
+
+    /// Set the maximum number of pages to extract from in a crawl; 0 for no
+    /// limit.
+    pub fn set_max_pages(&mut self, max_pages: usize) {
+        self.max_pages = max_pages;
+    }
+
+    /// Returns the maximum number of bytes of documents to read in a crawl.
+    pub fn max_bytes(&self) -> u64 {
+        self.max_bytes
+    }
+
+    /// Set the maximum number of bytes of documents to read in a crawl; 0
+    /// for no limit.
+    pub fn set_max_bytes(&mut self, max_bytes: u64) {
+        self.max_bytes = max_bytes;
+    }
+
+    /// Returns the maximum duration of a crawl.
+    pub fn max_duration(&self) -> Option<Duration> {
+        self.max_duration
+    }
+
+    /// Set the maximum duration of a crawl; none for no limit.
+    pub fn set_max_duration(&mut self, max_duration: Option<Duration>) {
+        self.max_duration = max_duration;
+    }
+
+    /// Returns the TLS configuration for requests.
+    pub fn tls(&self) -> &TlsSettings {
+        &self.tls
+    }
+
+    /// Set the TLS configuration for requests.
+    pub fn set_tls(&mut self, tls: TlsSettings) {
+        self.tls = tls;
+    }
+
+    /// Returns whether or not documents exceeding the maximum size are truncated.
+    pub fn truncate_oversized(&self) -> bool {
+        self.truncate_oversized
+    }
+
+    /// Set whether or not to truncate documents exceeding the maximum size,
+    /// rather than skipping them.
+    pub fn set_truncate_oversized(&mut self, truncate: bool) {
+        self.truncate_oversized = truncate;
+    }
 }
 
 #[derive(Copy, Debug, Clone)]
 pub enum CrawlMode {
     Web,
     Local,
+    Archive,
+    Har,
 }
 
 /// Display implementation.
@@ -690,6 +2214,8 @@ impl std::fmt::Display for CrawlMode {
         match self {
             Self::Web => write!(f, "Web"),
             Self::Local => write!(f, "Local"),
+            Self::Archive => write!(f, "Archive"),
+            Self::Har => write!(f, "Har"),
         }
     }
 }
@@ -699,7 +2225,294 @@ impl PartialEq for CrawlMode {
         match (self, other) {
             (Self::Web, Self::Web) => true,
             (Self::Local, Self::Local) => true,
+            (Self::Archive, Self::Archive) => true,
+            (Self::Har, Self::Har) => true,
             _ => false,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indicatif::ProgressDrawTarget;
+    use std::collections::HashMap;
+    use std::io::ErrorKind;
+    use std::net::TcpListener;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::sync::broadcast;
+
+    fn doc(content_type: &'static str, body: impl Into<Bytes>) -> Document {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+        Document::with_headers(headers, body.into())
+    }
+
+    fn options(base: &Url, mode: CrawlMode) -> CrawlOptions {
+        let mut copts = CrawlOptions::new(
+            base,
+            2,
+            false,
+            false,
+            SitePolicy::Same,
+            100,
+            4,
+            mode,
+            None,
+            None,
+        );
+        copts.set_retry_policy(RetryPolicy::new(
+            2,
+            Duration::from_millis(1),
+            Duration::from_millis(1),
+        ));
+        copts
+    }
+
+    /// Returns a crawler with the given options, along with the sender its
+    /// shutdown listens to; dropping the sender shuts the crawler down.
+    fn build_crawler(copts: CrawlOptions) -> (Crawler, broadcast::Sender<()>) {
+        let eopts = ExtractOptions::new(3, usize::MAX, false, false, Vec::new());
+        let (notify, receiver) = broadcast::channel(1);
+        let crawler = Crawler::new(
+            copts,
+            eopts,
+            UrlDb::new(),
+            WordDb::new(),
+            Shutdown::new(receiver),
+            MultiProgress::with_draw_target(ProgressDrawTarget::hidden()),
+        )
+        .ok()
+        .unwrap();
+        (crawler, notify)
+    }
+
+    fn response(status: &str, headers: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            headers,
+            body.len(),
+            body
+        )
+    }
+
+    /// Serves each request with the response returned for its path, given
+    /// how many times the path was requested before; returns the url served
+    /// from.
+    async fn serve(handler: fn(&str, usize) -> String) -> Url {
+        serve_slowly(Duration::ZERO, handler).await
+    }
+
+    /// Serves like `serve`, holding each response back for the given delay.
+    async fn serve_slowly(delay: Duration, handler: fn(&str, usize) -> String) -> Url {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .ok()
+            .unwrap();
+        let base = Url::parse(&format!("http://{}/", listener.local_addr().ok().unwrap()))
+            .ok()
+            .unwrap();
+        tokio::spawn(async move {
+            let mut hits: HashMap<String, usize> = HashMap::new();
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut head = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match stream.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => head.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let head = String::from_utf8_lossy(&head);
+                let path = head.split_whitespace().nth(1).unwrap_or("/").to_string();
+                let n = hits.entry(path.clone()).or_insert(0);
+                let res = handler(&path, *n);
+                *n += 1;
+                tokio::spawn(async move {
+                    sleep(delay).await;
+                    let _ = stream.write_all(res.as_bytes()).await;
+                });
+            }
+        });
+        base
+    }
+
+    #[tokio::test]
+    async fn host_permits() {
+        let page = |_: &str, _| response("200 OK", "Content-Type: text/html\r\n", "<p>page</p>");
+        let slow = serve_slowly(Duration::from_secs(2), page).await;
+        // a second host, served from the same address
+        let mut free = serve(page).await;
+        free.set_host(Some("localhost")).ok().unwrap();
+
+        let slow_urls: Vec<Url> = (0..3)
+            .map(|i| slow.join(&i.to_string()).ok().unwrap())
+            .collect();
+        let free_urls: Vec<Url> = (0..3)
+            .map(|i| free.join(&i.to_string()).ok().unwrap())
+            .collect();
+        let mut copts = CrawlOptions::new(
+            &slow,
+            1,
+            false,
+            false,
+            SitePolicy::Same,
+            100,
+            2,
+            CrawlMode::Web,
+            None,
+            None,
+        );
+        copts.set_host_limit_concurrent(1);
+        copts.set_ignore_robots(true);
+        copts.set_seeds(slow_urls.iter().chain(free_urls.iter()).cloned().collect());
+        let (mut crawler, _notify) = build_crawler(copts);
+
+        // urls waiting on the slow host don't hold up those of the free host
+        let _ = tokio::time::timeout(Duration::from_secs(1), crawler.crawl()).await;
+        let visited: HashSet<String> = crawler.urldb.visited_urls_iter().collect();
+        assert!(free_urls.iter().all(|u| visited.contains(u.as_str())));
+        assert!(slow_urls.iter().all(|u| !visited.contains(u.as_str())));
+    }
+
+    #[tokio::test]
+    async fn robots_redirects() {
+        let base = serve(|path, _| match path {
+            "/robots.txt" => response("301 Moved Permanently", "Location: /a/robots.txt\r\n", ""),
+            "/a/robots.txt" => response("302 Found", "Location: /b/robots.txt\r\n", ""),
+            "/b/robots.txt" => response("200 OK", "", "User-agent: *\nDisallow: /private\n"),
+            _ => response("404 Not Found", "", ""),
+        })
+        .await;
+        // redirects are followed even where the crawl's policy stops short
+        let mut copts = options(&base, CrawlMode::Web);
+        copts.set_max_redirects(0);
+        let (crawler, _notify) = build_crawler(copts);
+        let mut spider = crawler.build_spider();
+        let public = base.join("/public").ok().unwrap();
+        let private = base.join("/private").ok().unwrap();
+        assert_eq!(spider.matches_robots(&public).await, Some(true));
+        assert_eq!(spider.matches_robots(&private).await, Some(false));
+
+        // endless redirects leave robots.txt unresolved, allowing all
+        let base =
+            serve(|path, _| response("302 Found", &format!("Location: {}x\r\n", path), "")).await;
+        let (crawler, _notify) = build_crawler(options(&base, CrawlMode::Web));
+        let mut spider = crawler.build_spider();
+        let private = base.join("/private").ok().unwrap();
+        assert_eq!(spider.matches_robots(&private).await, Some(true));
+    }
+
+    #[tokio::test]
+    async fn discovery_redirects() {
+        let base = serve(|path, _| match path {
+            "/robots.txt" => response("301 Moved Permanently", "Location: /a/robots.txt\r\n", ""),
+            "/a/robots.txt" => response("302 Found", "Location: /b/robots.txt\r\n", ""),
+            "/b/robots.txt" => response("200 OK", "", "User-agent: *\nDisallow: /private\n"),
+            "/sitemap.xml" => response("301 Moved Permanently", "Location: /a/sitemap.xml\r\n", ""),
+            "/a/sitemap.xml" => response("302 Found", "Location: /b/sitemap.xml\r\n", ""),
+            "/b/sitemap.xml" => response(
+                "200 OK",
+                "",
+                "<urlset><url><loc>https://example.com/found</loc></url></urlset>",
+            ),
+            _ => response("404 Not Found", "", ""),
+        })
+        .await;
+        // the crawl's redirect policy gives up before either is reached
+        let mut copts = options(&base, CrawlMode::Web);
+        copts.set_max_redirects(1);
+        copts.set_sitemaps(true);
+        let (crawler, _notify) = build_crawler(copts);
+        let mut spider = crawler.build_spider();
+        let private = base.join("/private").ok().unwrap();
+        assert_eq!(spider.matches_robots(&private).await, Some(false));
+        spider.urls_from_sitemaps(&base).await;
+        assert!(crawler
+            .urldb
+            .unvisited_urls_iter()
+            .any(|u| u == "https://example.com/found"));
+    }
+
+    #[tokio::test]
+    async fn robots_transient_error() {
+        let base = serve(|path, n| match (path, n) {
+            ("/robots.txt", 0) => response("503 Service Unavailable", "", ""),
+            ("/robots.txt", _) => response("200 OK", "", "User-agent: *\nDisallow: /private\n"),
+            _ => response("404 Not Found", "", ""),
+        })
+        .await;
+        let (crawler, _notify) = build_crawler(options(&base, CrawlMode::Web));
+        let mut spider = crawler.build_spider();
+        let public = base.join("/public").ok().unwrap();
+        let private = base.join("/private").ok().unwrap();
+        assert_eq!(spider.matches_robots(&public).await, Some(true));
+        assert_eq!(spider.matches_robots(&private).await, Some(false));
+
+        // out of retries, the host's urls wait for robots.txt to be fetched again
+        let base = serve(|_, _| response("503 Service Unavailable", "", "")).await;
+        let mut copts = options(&base, CrawlMode::Web);
+        copts.set_retry_policy(RetryPolicy::new(0, Duration::ZERO, Duration::ZERO));
+        let (crawler, _notify) = build_crawler(copts);
+        let mut spider = crawler.build_spider();
+        let page = base.join("/page").ok().unwrap();
+        assert_eq!(spider.matches_robots(&page).await, None);
+        assert!(spider.crawl_url(page.as_str()).await.is_none());
+        assert!(crawler
+            .urldb
+            .unvisited_urls_iter()
+            .any(|u| u == page.as_str()));
+        assert!(crawler.urldb.errored_urls_iter().next().is_none());
+    }
+
+    #[tokio::test]
+    async fn archive_offline() {
+        // nothing is ever accepted, so any request would be left pending
+        let listener = TcpListener::bind("127.0.0.1:0").ok().unwrap();
+        listener.set_nonblocking(true).ok().unwrap();
+        let base = Url::parse(&format!("http://{}/", listener.local_addr().ok().unwrap()))
+            .ok()
+            .unwrap();
+        let at = |path: &str| base.join(path).ok().unwrap();
+
+        let path = std::env::temp_dir()
+            .join(format!("wdict-test-{}-offline.warc", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        let writer = WarcWriter::create(&path, false, &base).ok().unwrap();
+        let robots = format!("User-agent: *\nSitemap: {}\n", at("/map.xml"));
+        let map = format!(
+            "<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\"><url><loc>{}</loc></url></urlset>",
+            at("/listed")
+        );
+        let records = [
+            (base.clone(), doc("text/html", "<p>home</p>")),
+            (at("/robots.txt"), doc("text/plain", robots)),
+            (at("/map.xml"), doc("application/xml", map)),
+            (at("/listed"), doc("text/html", "<p>sitemapped</p>")),
+        ];
+        for (u, d) in records.iter() {
+            assert!(writer.write_response(u, StatusCode::OK, d).is_ok());
+        }
+        drop(writer);
+        let archive = WarcArchive::open(&path, &UrlNormalizer::default())
+            .ok()
+            .unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let mut copts = options(&base, CrawlMode::Archive);
+        copts.set_archive(Some(archive));
+        copts.set_sitemaps(true);
+        let (mut crawler, _notify) = build_crawler(copts);
+        assert!(crawler.crawl().await.is_ok());
+
+        let words: Vec<String> = crawler.worddb.iter().collect();
+        assert!(words.contains(&"home".to_string()));
+        assert!(words.contains(&"sitemapped".to_string()));
+        assert!(matches!(
+            listener.accept(),
+            Err(e) if e.kind() == ErrorKind::WouldBlock
+        ));
+    }
+}
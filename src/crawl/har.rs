@@ -0,0 +1,289 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use bytes::Bytes;
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE,
+    TRANSFER_ENCODING,
+};
+use reqwest::{StatusCode, Url};
+use serde::Deserialize;
+use std::fs;
+use std::sync::Arc;
+
+use crate::error::Error;
+
+/// An entry of a HAR (HTTP Archive) file; a request along with the response
+/// it was served.
+#[derive(Debug, Clone)]
+pub struct HarEntry {
+    /// Url of the request.
+    pub url: Url,
+    /// Status code of the response; none if no response was received (e.g.
+    /// the request was blocked or aborted).
+    pub status: Option<StatusCode>,
+    /// Headers of the response, excluding those describing transfer or
+    /// content encodings; the content of HAR files is decoded.
+    pub headers: HeaderMap,
+    /// Body of the response; empty if it was not captured.
+    pub body: Bytes,
+}
+
+/// Entries of a HAR file, in the order they were captured (e.g. by browser
+/// developer tools or an intercepting proxy).
+#[derive(Debug, Clone)]
+pub struct Har(Arc<Vec<HarEntry>>);
+
+/// Root of a HAR file.
+#[derive(Deserialize)]
+struct HarFile<E> {
+    log: HarLog<E>,
+}
+
+/// Log of a HAR file, holding its entries.
+#[derive(Deserialize)]
+struct HarLog<E> {
+    #[serde(default = "Vec::new")]
+    entries: Vec<E>,
+}
+
+/// An entry of a HAR file, as serialized.
+#[derive(Deserialize)]
+struct RawEntry {
+    request: RawRequest,
+    response: RawResponse,
+}
+
+/// Request of an entry, as serialized; only its url is of interest.
+#[derive(Deserialize)]
+struct RawRequest {
+    url: String,
+}
+
+/// Response of an entry, as serialized.
+#[derive(Deserialize)]
+struct RawResponse {
+    #[serde(default)]
+    status: i64,
+    #[serde(default)]
+    headers: Vec<RawHeader>,
+    #[serde(default)]
+    content: RawContent,
+}
+
+/// Header of a response, as serialized.
+#[derive(Deserialize)]
+struct RawHeader {
+    name: String,
+    value: String,
+}
+
+/// Content of a response, as serialized.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct RawContent {
+    #[serde(default)]
+    mime_type: Option<String>,
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    encoding: Option<String>,
+}
+
+/// Entry of a HAR file, reduced to its url; for finding where a capture
+/// started without decoding any content.
+#[derive(Deserialize)]
+struct UrlEntry {
+    request: RawRequest,
+}
+
+impl Har {
+    /// Reads the HAR file at the given path.
+    pub fn open(path: &str) -> Result<Self, Error> {
+        Self::parse(&fs::read(path)?)
+    }
+
+    /// Parses the given HAR file; entries with invalid urls are skipped.
+    pub fn parse(buf: &[u8]) -> Result<Self, Error> {
+        let har: HarFile<RawEntry> = serde_json::from_slice(buf)?;
+        let entries = har
+            .log
+            .entries
+            .into_iter()
+            .filter_map(|e| entry(e).ok())
+            .collect();
+        Ok(Self(Arc::new(entries)))
+    }
+
+    /// Returns the entries, in the order they were captured.
+    pub fn entries(&self) -> &[HarEntry] {
+        &self.0
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns whether or not there are no entries.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Returns the url of the first request captured in the HAR file at the
+/// given path, if any; typically the page browsing started from.
+pub fn har_starting_url(path: &str) -> Result<Option<Url>, Error> {
+    let har: HarFile<UrlEntry> = serde_json::from_slice(&fs::read(path)?)?;
+    Ok(har
+        .log
+        .entries
+        .into_iter()
+        .find_map(|e| Url::parse(&e.request.url).ok()))
+}
+
+/// Returns the entry of a HAR file, decoding its content.
+fn entry(raw: RawEntry) -> Result<HarEntry, Error> {
+    let url = Url::parse(&raw.request.url)?;
+    let response = raw.response;
+    // 0 (or -1) marks that no response was received
+    let status = u16::try_from(response.status)
+        .ok()
+        .and_then(|s| StatusCode::from_u16(s).ok());
+
+    let mut headers = HeaderMap::new();
+    for h in response.headers.iter() {
+        // pseudo-headers of HTTP/2 (e.g. `:status`) are not valid names
+        if let (Ok(n), Ok(v)) = (
+            HeaderName::from_bytes(h.name.trim().as_bytes()),
+            HeaderValue::from_str(h.value.trim()),
+        ) {
+            headers.append(n, v);
+        }
+    }
+    for name in [CONTENT_ENCODING, CONTENT_LENGTH, TRANSFER_ENCODING] {
+        headers.remove(name);
+    }
+    let content = response.content;
+    if !headers.contains_key(CONTENT_TYPE) {
+        if let Some(v) = content
+            .mime_type
+            .as_deref()
+            .filter(|m| !m.is_empty())
+            .and_then(|m| HeaderValue::from_str(m).ok())
+        {
+            headers.insert(CONTENT_TYPE, v);
+        }
+    }
+
+    let text = content.text.unwrap_or_default();
+    let body = match content.encoding.as_deref() {
+        Some(e) if e.eq_ignore_ascii_case("base64") => {
+            // captures may wrap lines of base64
+            let text: String = text.split_whitespace().collect();
+            STANDARD.decode(text).map_err(|e| {
+                Error::GeneralError(format!("invalid base64 content for {}: {}", url, e))
+            })?
+        }
+        _ => text.into_bytes(),
+    };
+
+    Ok(HarEntry {
+        url,
+        status,
+        headers,
+        body: Bytes::from(body),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HAR: &str = r#"{
+  "log": {
+    "version": "1.2",
+    "creator": {"name": "test", "version": "1"},
+    "pages": [{"id": "page_1", "title": "https://example.com/"}],
+    "entries": [
+      {
+        "request": {"method": "GET", "url": "https://example.com/", "headers": []},
+        "response": {
+          "status": 200,
+          "headers": [
+            {"name": ":status", "value": "200"},
+            {"name": "Content-Type", "value": "text/html; charset=utf-8"},
+            {"name": "Content-Encoding", "value": "br"},
+            {"name": "Content-Length", "value": "12"}
+          ],
+          "content": {"size": 25, "mimeType": "text/html", "text": "<p>hello world</p>"}
+        }
+      },
+      {
+        "request": {"method": "GET", "url": "https://example.com/data.txt"},
+        "response": {
+          "status": 200,
+          "headers": [],
+          "content": {"mimeType": "text/plain", "text": "ZW5jb2Rl\nZCB0ZXh0", "encoding": "base64"}
+        }
+      },
+      {
+        "request": {"method": "GET", "url": "not a url"},
+        "response": {"status": 200, "headers": [], "content": {}}
+      },
+      {
+        "request": {"method": "GET", "url": "https://tracker.example.org/p.gif"},
+        "response": {"status": 0, "headers": [], "content": {"size": 0, "mimeType": ""}}
+      },
+      {
+        "request": {"method": "GET", "url": "https://example.com/bad"},
+        "response": {"status": 200, "content": {"text": "!!", "encoding": "base64"}}
+      }
+    ]
+  }
+}"#;
+
+    #[test]
+    fn parse() {
+        let har = Har::parse(HAR.as_bytes()).ok().unwrap();
+        assert_eq!(har.len(), 3);
+        let entries = har.entries();
+
+        assert_eq!(entries[0].url.as_str(), "https://example.com/");
+        assert_eq!(entries[0].status, Some(StatusCode::OK));
+        assert_eq!(
+            entries[0].headers.get(CONTENT_TYPE).unwrap(),
+            "text/html; charset=utf-8"
+        );
+        assert!(entries[0].headers.get(CONTENT_ENCODING).is_none());
+        assert!(entries[0].headers.get(CONTENT_LENGTH).is_none());
+        assert_eq!(&entries[0].body[..], b"<p>hello world</p>");
+
+        assert_eq!(entries[1].headers.get(CONTENT_TYPE).unwrap(), "text/plain");
+        assert_eq!(&entries[1].body[..], b"encoded text");
+
+        assert_eq!(entries[2].status, None);
+        assert!(entries[2].headers.get(CONTENT_TYPE).is_none());
+        assert!(entries[2].body.is_empty());
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(Har::parse(b"{}").is_err());
+        assert!(Har::parse(b"not json").is_err());
+        let empty = Har::parse(br#"{"log": {"version": "1.2"}}"#).ok().unwrap();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn starting_url() {
+        let path = std::env::temp_dir().join(format!("wdict-test-{}.har", std::process::id()));
+        let path = path.to_string_lossy().to_string();
+        fs::write(&path, HAR).ok().unwrap();
+        let url = har_starting_url(&path).ok().unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(
+            url.map(|u| u.to_string()),
+            Some("https://example.com/".to_string())
+        );
+    }
+}
@@ -0,0 +1,335 @@
+use ratelimit::Ratelimiter;
+use reqwest::Url;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::sleep;
+
+use crate::error::Error;
+
+/// Returns a new rate limiter allowing the given number of requests per second.
+pub fn build_limiter(req_per_sec: u64) -> Result<Ratelimiter, Error> {
+    let tokens = if req_per_sec < 1 { 1 } else { req_per_sec };
+    let limiter = Ratelimiter::builder(tokens, Duration::from_secs(1))
+        .max_tokens(tokens)
+        .initial_available(tokens / 2)
+        .build()?;
+    Ok(limiter)
+}
+
+/// Returns the current interval between requests allowed by the rate limiter.
+pub fn limiter_interval(limiter: &Ratelimiter) -> Duration {
+    let amount = u32::try_from(limiter.refill_amount().max(1)).unwrap_or(u32::MAX);
+    limiter.refill_interval() / amount
+}
+
+/// Slow the rate limiter down to one request per the given interval, if it is
+/// not already slower than that; returns whether or not the pace changed.
+pub fn slow_limiter(limiter: &Ratelimiter, interval: Duration) -> Result<bool, Error> {
+    if interval <= limiter_interval(limiter) {
+        return Ok(false);
+    }
+    limiter.set_refill_amount(1)?;
+    limiter.set_max_tokens(1)?;
+    limiter.set_available(limiter.available().min(1))?;
+    limiter.set_refill_interval(interval)?;
+    Ok(true)
+}
+
+/// Returns the key used to group urls by host.
+fn host_key(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(u) => u.host_str().unwrap_or("").to_string(),
+        Err(_) => String::new(),
+    }
+}
+
+/// Orders urls so that consecutive urls cycle through their hosts, rather
+/// than clustering on any single host; hosts keep their first-seen order.
+pub fn interleave_by_host(urls: impl Iterator<Item = String>) -> Vec<String> {
+    let mut hosts: Vec<String> = Vec::new();
+    let mut by_host: HashMap<String, VecDeque<String>> = HashMap::new();
+    for url in urls {
+        let key = host_key(&url);
+        if !by_host.contains_key(&key) {
+            hosts.push(key.clone());
+        }
+        by_host.entry(key).or_default().push_back(url);
+    }
+
+    let mut ordered = Vec::new();
+    let mut remaining = true;
+    while remaining {
+        remaining = false;
+        for h in hosts.iter() {
+            if let Some(u) = by_host.get_mut(h).and_then(|q| q.pop_front()) {
+                ordered.push(u);
+                remaining = true;
+            }
+        }
+    }
+    ordered
+}
+
+/// Rate limit and concurrency limit for a single host.
+pub struct HostLimit {
+    limiter: Ratelimiter,
+    semaphore: Arc<Semaphore>,
+}
+
+impl HostLimit {
+    /// Returns the rate limiter of the host.
+    pub fn limiter(&self) -> &Ratelimiter {
+        &self.limiter
+    }
+
+    /// Acquire a permit for one concurrent request to the host.
+    pub async fn acquire(&self) -> Option<OwnedSemaphorePermit> {
+        self.semaphore.clone().acquire_owned().await.ok()
+    }
+
+    /// Wait until the host's rate limit allows another request.
+    pub async fn wait(&self) {
+        while let Err(dur) = self.limiter.try_wait() {
+            sleep(dur).await;
+        }
+    }
+}
+
+/// Per-host limits, layered under the global limits; each host's limits are
+/// created the first time the host is seen.
+pub struct HostLimits {
+    req_per_sec: u64,
+    limit_concurrent: usize,
+    hosts: Arc<Mutex<HashMap<String, Arc<HostLimit>>>>,
+}
+
+impl Clone for HostLimits {
+    /// Returns a clone/handle of the given HostLimits.
+    fn clone(&self) -> Self {
+        HostLimits {
+            req_per_sec: self.req_per_sec,
+            limit_concurrent: self.limit_concurrent,
+            hosts: Arc::clone(&self.hosts),
+        }
+    }
+}
+
+impl HostLimits {
+    /// Returns a new HostLimits instance, applying the given limits to each host.
+    pub fn new(req_per_sec: u64, limit_concurrent: usize) -> Self {
+        Self {
+            req_per_sec,
+            limit_concurrent,
+            hosts: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Returns the limits for the host of the given url.
+    pub fn get(&self, url: &Url) -> Result<Arc<HostLimit>, Error> {
+        let mut hm: MutexGuard<HashMap<String, Arc<HostLimit>>> = match self.hosts.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let key = url.host_str().unwrap_or("").to_string();
+        if let Some(h) = hm.get(&key) {
+            return Ok(h.clone());
+        }
+        let host = Arc::new(HostLimit {
+            limiter: build_limiter(self.req_per_sec)?,
+            semaphore: Arc::new(Semaphore::new(self.limit_concurrent.max(1))),
+        });
+        hm.insert(key, host.clone());
+        Ok(host)
+    }
+}
+
+/// Which budget of a crawl was exhausted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BudgetExhausted {
+    /// Maximum number of pages.
+    Pages(usize),
+    /// Maximum number of bytes.
+    Bytes(u64),
+    /// Maximum duration.
+    Duration(Duration),
+}
+
+impl fmt::Display for BudgetExhausted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Pages(max) => write!(f, "page budget of {} reached", max),
+            Self::Bytes(max) => write!(f, "byte budget of {} reached", max),
+            Self::Duration(max) => write!(f, "time budget of {}s reached", max.as_secs()),
+        }
+    }
+}
+
+/// Budgets bounding a crawl, shared between its tasks; pages are reserved
+/// before they are fetched, so concurrent requests never exceed the page
+/// budget, while bytes are counted once documents are read.
+#[derive(Debug, Clone)]
+pub struct CrawlBudget {
+    /// Maximum number of pages; 0 for no limit.
+    max_pages: usize,
+    /// Maximum number of bytes of documents; 0 for no limit.
+    max_bytes: u64,
+    /// Maximum duration of the crawl; none for no limit.
+    max_duration: Option<Duration>,
+    pages: Arc<AtomicUsize>,
+    bytes: Arc<AtomicU64>,
+    started: Instant,
+}
+
+impl CrawlBudget {
+    /// Returns a new CrawlBudget instance, starting the clock now.
+    pub fn new(max_pages: usize, max_bytes: u64, max_duration: Option<Duration>) -> Self {
+        Self {
+            max_pages,
+            max_bytes,
+            max_duration,
+            pages: Arc::new(AtomicUsize::new(0)),
+            bytes: Arc::new(AtomicU64::new(0)),
+            started: Instant::now(),
+        }
+    }
+
+    /// Returns the number of pages reserved or completed.
+    pub fn pages(&self) -> usize {
+        self.pages.load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of bytes of documents read.
+    pub fn bytes(&self) -> u64 {
+        self.bytes.load(Ordering::SeqCst)
+    }
+
+    /// Returns which budget is exhausted, if any.
+    pub fn exhausted(&self) -> Option<BudgetExhausted> {
+        if self.max_pages > 0 && self.pages() >= self.max_pages {
+            return Some(BudgetExhausted::Pages(self.max_pages));
+        }
+        if self.max_bytes > 0 && self.bytes() >= self.max_bytes {
+            return Some(BudgetExhausted::Bytes(self.max_bytes));
+        }
+        match self.max_duration {
+            Some(d) if self.started.elapsed() >= d => Some(BudgetExhausted::Duration(d)),
+            _ => None,
+        }
+    }
+
+    /// Reserve a page to fetch, unless a budget is exhausted; returns whether
+    /// or not the page was reserved.
+    pub fn reserve_page(&self) -> bool {
+        if self.max_bytes > 0 && self.bytes() >= self.max_bytes {
+            return false;
+        }
+        if matches!(self.max_duration, Some(d) if self.started.elapsed() >= d) {
+            return false;
+        }
+        self.pages
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |p| {
+                (self.max_pages == 0 || p < self.max_pages).then_some(p + 1)
+            })
+            .is_ok()
+    }
+
+    /// Release a reserved page that yielded no document.
+    pub fn release_page(&self) {
+        let _ = self
+            .pages
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |p| p.checked_sub(1));
+    }
+
+    /// Count the bytes of a document read for a reserved page.
+    pub fn add_bytes(&self, n: u64) {
+        self.bytes.fetch_add(n, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interleave() {
+        let urls = vec![
+            "https://a.com/1",
+            "https://a.com/2",
+            "https://a.com/3",
+            "https://b.com/1",
+            "not a url",
+            "https://c.com/1",
+            "https://b.com/2",
+        ];
+        assert_eq!(
+            interleave_by_host(urls.into_iter().map(String::from)),
+            vec![
+                "https://a.com/1",
+                "https://b.com/1",
+                "not a url",
+                "https://c.com/1",
+                "https://a.com/2",
+                "https://b.com/2",
+                "https://a.com/3",
+            ]
+        );
+    }
+
+    #[test]
+    fn slow_down() {
+        let limiter = build_limiter(10).ok().unwrap();
+        assert_eq!(limiter_interval(&limiter), Duration::from_millis(100));
+        assert!(slow_limiter(&limiter, Duration::from_secs(2)).ok().unwrap());
+        assert_eq!(limiter_interval(&limiter), Duration::from_secs(2));
+        assert!(!slow_limiter(&limiter, Duration::from_secs(1)).ok().unwrap());
+        assert_eq!(limiter_interval(&limiter), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn budget_pages() {
+        let budget = CrawlBudget::new(2, 0, None);
+        let shared = budget.clone();
+        assert!(budget.reserve_page());
+        assert!(shared.reserve_page());
+        assert!(!budget.reserve_page());
+        assert_eq!(budget.exhausted(), Some(BudgetExhausted::Pages(2)));
+        shared.release_page();
+        assert_eq!(budget.exhausted(), None);
+        assert!(budget.reserve_page());
+        assert_eq!(budget.pages(), 2);
+    }
+
+    #[test]
+    fn budget_bytes() {
+        let budget = CrawlBudget::new(0, 100, None);
+        assert!(budget.reserve_page());
+        budget.add_bytes(60);
+        assert_eq!(budget.exhausted(), None);
+        assert!(budget.reserve_page());
+        budget.add_bytes(60);
+        assert_eq!(budget.exhausted(), Some(BudgetExhausted::Bytes(100)));
+        assert!(!budget.reserve_page());
+        assert_eq!(budget.bytes(), 120);
+    }
+
+    #[test]
+    fn budget_duration() {
+        let budget = CrawlBudget::new(0, 0, Some(Duration::ZERO));
+        assert_eq!(
+            budget.exhausted(),
+            Some(BudgetExhausted::Duration(Duration::ZERO))
+        );
+        assert!(!budget.reserve_page());
+
+        let unlimited = CrawlBudget::new(0, 0, None);
+        assert_eq!(unlimited.exhausted(), None);
+        assert!(unlimited.reserve_page());
+        unlimited.release_page();
+        unlimited.release_page();
+        assert_eq!(unlimited.pages(), 0);
+    }
+}
@@ -0,0 +1,512 @@
+use regex::Regex;
+use reqwest::Url;
+use scraper::{node::Element, node::Node, Html};
+use std::sync::LazyLock;
+
+use crate::extract::{parse_content_type, FEED_MIME_TYPES};
+
+/// Extensions of assets referenced from css and js that are not worth
+/// fetching, as they are binary (images and fonts).
+const BINARY_ASSET_EXTENSIONS: [&str; 12] = [
+    "png", "jpg", "jpeg", "gif", "webp", "avif", "ico", "bmp", "woff", "woff2", "ttf", "otf",
+];
+
+/// Css `url(...)` and `@import "..."` references.
+static CSS_URL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?i)url\(\s*(?:"([^"]*)"|'([^']*)'|([^'")\s]*))\s*\)|@import\s+(?:"([^"]*)"|'([^']*)')"#,
+    )
+    .unwrap()
+});
+/// Js string literals, quoted with single, double, or back quotes.
+static JS_STRING: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#""((?:[^"\\\n]|\\.)*)"|'((?:[^'\\\n]|\\.)*)'|`((?:[^`\\]|\\.)*)`"#).unwrap()
+});
+/// Js `fetch(...)` and XHR `open(method, ...)` calls, of which the url may be relative.
+static JS_REQUEST: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"\bfetch\(\s*["'`]([^"'`\s]+)["'`]|\.open\(\s*["'][A-Za-z]+["']\s*,\s*["'`]([^"'`\s]+)["'`]"#,
+    )
+    .unwrap()
+});
+
+/// Sources of links within html documents.
+#[derive(Copy, Debug, Clone, PartialEq)]
+pub enum LinkSource {
+    /// `<a href>` and `<area href>` elements.
+    Anchor,
+    /// `<iframe src>` and `<frame src>` elements.
+    Frame,
+    /// `<form action>` elements submitted via GET.
+    Form,
+    /// `<meta http-equiv="refresh">` redirects.
+    Refresh,
+    /// `srcset` candidates of `<img>` and `<source>` elements.
+    Srcset,
+    /// `<script src>` elements.
+    Script,
+    /// `<link href>` elements.
+    Link,
+}
+
+/// Kinds of text assets that urls may be discovered within.
+#[derive(Copy, Debug, Clone, PartialEq)]
+pub enum AssetKind {
+    /// Stylesheets.
+    Css,
+    /// Javascript.
+    Js,
+}
+
+/// Display implementation.
+impl std::fmt::Display for AssetKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Css => write!(f, "Css"),
+            Self::Js => write!(f, "Js"),
+        }
+    }
+}
+
+/// Returns the kind of asset of a document, based on its declared mime type,
+/// or the extension of its url when no type was declared.
+pub fn asset_kind(mime: Option<&str>, url: &Url) -> Option<AssetKind> {
+    match mime {
+        Some("text/css") => Some(AssetKind::Css),
+        Some(
+            "application/javascript"
+            | "application/x-javascript"
+            | "application/ecmascript"
+            | "text/javascript"
+            | "text/ecmascript",
+        ) => Some(AssetKind::Js),
+        Some(_) => None,
+        None => match extension(url).as_deref() {
+            Some("css") => Some(AssetKind::Css),
+            Some("js" | "mjs") => Some(AssetKind::Js),
+            _ => None,
+        },
+    }
+}
+
+/// Returns the lowercase extension of the last segment of the url's path, if any.
+fn extension(url: &Url) -> Option<String> {
+    let segment = url.path_segments()?.next_back()?;
+    let (_, ext) = segment.rsplit_once('.')?;
+    Some(ext.to_lowercase())
+}
+
+/// Returns whether or not the url is worth fetching when referenced from an
+/// asset; binary assets (images and fonts) are not.
+pub fn is_text_asset(url: &Url) -> bool {
+    !extension(url).is_some_and(|e| BINARY_ASSET_EXTENSIONS.contains(&e.as_str()))
+}
+
+/// Returns the raw (unresolved) urls referenced by the given asset.
+pub fn asset_links(text: &str, kind: AssetKind) -> Vec<String> {
+    match kind {
+        AssetKind::Css => css_links(text),
+        AssetKind::Js => js_links(text),
+    }
+}
+
+/// Returns the raw urls of `url(...)` and `@import` references in css;
+/// inline `data:` urls are ignored.
+fn css_links(text: &str) -> Vec<String> {
+    CSS_URL
+        .captures_iter(text)
+        .filter_map(|c| c.iter().skip(1).flatten().next())
+        .map(|m| m.as_str().trim())
+        .filter(|u| !u.is_empty() && !u.starts_with("data:") && !u.starts_with('#'))
+        .map(|u| u.to_string())
+        .collect()
+}
+
+/// Returns the raw urls found in js; string literals that hold absolute
+/// http(s) urls or root-relative paths, as well as any url passed to
+/// `fetch(...)` or an XHR's `open(...)`.
+fn js_links(text: &str) -> Vec<String> {
+    let literals = JS_STRING
+        .captures_iter(text)
+        .filter_map(|c| c.iter().skip(1).flatten().next())
+        .map(|m| m.as_str().replace("\\/", "/"))
+        .filter(|s| is_js_url(s));
+    let requests = JS_REQUEST
+        .captures_iter(text)
+        .filter_map(|c| c.iter().skip(1).flatten().next())
+        .map(|m| m.as_str().to_string())
+        .filter(|s| !s.contains("${"));
+
+    let mut links: Vec<String> = Vec::new();
+    for l in literals.chain(requests) {
+        if !links.contains(&l) {
+            links.push(l);
+        }
+    }
+    links
+}
+
+/// Returns whether or not a js string literal looks like a url worth following.
+fn is_js_url(s: &str) -> bool {
+    if s.len() < 2 || s.contains(char::is_whitespace) || s.contains("${") {
+        return false;
+    }
+    let lower = s.to_lowercase();
+    if lower.starts_with("http://") || lower.starts_with("https://") {
+        return Url::parse(s).is_ok();
+    }
+    // root-relative paths, but not protocol-relative urls or comment-like strings
+    s.starts_with('/')
+        && !s.starts_with("//")
+        && !s.starts_with("/*")
+        && s[1..].starts_with(|c: char| c.is_alphanumeric() || c == '_' || c == '-' || c == '.')
+}
+
+/// Display implementation.
+impl std::fmt::Display for LinkSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Anchor => write!(f, "Anchor"),
+            Self::Frame => write!(f, "Frame"),
+            Self::Form => write!(f, "Form"),
+            Self::Refresh => write!(f, "Refresh"),
+            Self::Srcset => write!(f, "Srcset"),
+            Self::Script => write!(f, "Script"),
+            Self::Link => write!(f, "Link"),
+        }
+    }
+}
+
+/// Returns the url that relative links in the document resolve against; the
+/// first `<base href>` if present, otherwise the page's own url.
+pub fn base_url(doc: &Html, page: &Url) -> Url {
+    doc.root_element()
+        .descendants()
+        .filter_map(|d| match d.value() {
+            Node::Element(elem) if elem.name() == "base" => elem.attr("href"),
+            _ => None,
+        })
+        .next()
+        .and_then(|href| page.join(href.trim()).ok())
+        .unwrap_or(page.clone())
+}
+
+/// Returns the raw (unresolved) links held by the given element, along with
+/// where they were found.
+pub fn element_links(elem: &Element) -> Vec<(LinkSource, String)> {
+    let mut links = Vec::new();
+    let mut push = |source, href: Option<&str>| {
+        if let Some(h) = href.map(|h| h.trim()).filter(|h| !h.is_empty()) {
+            links.push((source, h.to_string()));
+        }
+    };
+    match elem.name() {
+        "a" | "area" => push(LinkSource::Anchor, elem.attr("href")),
+        "link" => push(LinkSource::Link, elem.attr("href")),
+        "script" => push(LinkSource::Script, elem.attr("src")),
+        "iframe" | "frame" => push(LinkSource::Frame, elem.attr("src")),
+        "form" => {
+            let method = elem.attr("method").unwrap_or("get");
+            if method.trim().eq_ignore_ascii_case("get") {
+                push(LinkSource::Form, elem.attr("action"));
+            }
+        }
+        "meta" => {
+            let is_refresh = elem
+                .attr("http-equiv")
+                .is_some_and(|h| h.trim().eq_ignore_ascii_case("refresh"));
+            if is_refresh {
+                push(
+                    LinkSource::Refresh,
+                    elem.attr("content").and_then(refresh_url),
+                );
+            }
+        }
+        "img" | "source" => {
+            if let Some(srcset) = elem.attr("srcset") {
+                srcset_urls(srcset).for_each(|u| push(LinkSource::Srcset, Some(u)));
+            }
+        }
+        _ => {}
+    }
+    links
+}
+
+/// Returns whether or not the `rel` attribute of the given element holds the
+/// given link relation; `rel` is a space separated list of relations.
+pub fn has_rel(elem: &Element, relation: &str) -> bool {
+    elem.attr("rel").is_some_and(|rel| {
+        rel.split_ascii_whitespace()
+            .any(|t| t.eq_ignore_ascii_case(relation))
+    })
+}
+
+/// Returns whether or not the given `<link>` element points to a stylesheet.
+pub fn is_stylesheet_link(elem: &Element) -> bool {
+    has_rel(elem, "stylesheet") || has_destination(elem, "style")
+}
+
+/// Returns whether or not the given `<link>` element points to a script.
+pub fn is_script_link(elem: &Element) -> bool {
+    has_rel(elem, "modulepreload") || has_destination(elem, "script")
+}
+
+/// Returns whether or not the given `<link>` element points to an RSS or
+/// Atom feed, as in `<link rel="alternate" type="application/rss+xml">`.
+pub fn is_feed_link(elem: &Element) -> bool {
+    has_rel(elem, "alternate")
+        && elem.attr("type").is_some_and(|t| {
+            let (mime, _) = parse_content_type(t);
+            mime.is_some_and(|m| FEED_MIME_TYPES.contains(&m.as_str()))
+        })
+}
+
+/// Returns whether or not the `as` attribute of the given element (used when
+/// preloading) matches the given destination.
+fn has_destination(elem: &Element, destination: &str) -> bool {
+    elem.attr("as")
+        .is_some_and(|a| a.trim().eq_ignore_ascii_case(destination))
+}
+
+/// Returns the url from the content of a `<meta http-equiv="refresh">`
+/// element, such as `5; url=/next`.
+fn refresh_url(content: &str) -> Option<&str> {
+    let (_, rest) = content.split_once([';', ','])?;
+    let rest = rest.trim_start();
+    if !rest.get(..3).is_some_and(|p| p.eq_ignore_ascii_case("url")) {
+        return None;
+    }
+    let url = rest[3..].trim_start().strip_prefix('=')?.trim();
+    Some(url.trim_matches(|c| c == '"' || c == '\''))
+}
+
+/// Returns the urls of the image candidates in a `srcset` attribute, such as
+/// `a.png 1x, b.png 2x`.
+fn srcset_urls(srcset: &str) -> impl Iterator<Item = &str> {
+    srcset
+        .split(',')
+        .filter_map(|candidate| candidate.split_whitespace().next())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r#"<html><head>
+<base href="https://cdn.example.com/root/">
+<meta http-equiv="Refresh" content="5; URL='/moved'">
+<meta name="description" content="0; url=/nope">
+<link rel="stylesheet" href="style.css">
+<script src="app.js"></script>
+<script>var inline = true;</script>
+</head><body>
+<a href="page.html">page</a>
+<a>no href</a>
+<map><area href="/area" alt="area"></map>
+<iframe src="frame.html"></iframe>
+<form action="/search"></form>
+<form action="/login" method="POST"></form>
+<img src="a.png" srcset="a-1x.png 1x, a-2x.png 2x">
+<picture><source srcset="b.webp"></picture>
+</body></html>"#;
+
+    #[test]
+    fn links() {
+        let doc = Html::parse_document(PAGE);
+        let links: Vec<(LinkSource, String)> = doc
+            .root_element()
+            .descendants()
+            .filter_map(|d| d.value().as_element().map(element_links))
+            .flatten()
+            .collect();
+        let exp = vec![
+            (LinkSource::Refresh, "/moved"),
+            (LinkSource::Link, "style.css"),
+            (LinkSource::Script, "app.js"),
+            (LinkSource::Anchor, "page.html"),
+            (LinkSource::Anchor, "/area"),
+            (LinkSource::Frame, "frame.html"),
+            (LinkSource::Form, "/search"),
+            (LinkSource::Srcset, "a-1x.png"),
+            (LinkSource::Srcset, "a-2x.png"),
+            (LinkSource::Srcset, "b.webp"),
+        ];
+        assert_eq!(
+            links,
+            exp.into_iter()
+                .map(|(s, u)| (s, u.to_string()))
+                .collect::<Vec<(LinkSource, String)>>()
+        );
+    }
+
+    #[test]
+    fn frameset() {
+        let doc = Html::parse_document(
+            r#"<html><frameset><frame src="  top.html  "><frame src=""></frameset></html>"#,
+        );
+        let links: Vec<(LinkSource, String)> = doc
+            .root_element()
+            .descendants()
+            .filter_map(|d| d.value().as_element().map(element_links))
+            .flatten()
+            .collect();
+        assert_eq!(links, vec![(LinkSource::Frame, "top.html".to_string())]);
+    }
+
+    #[test]
+    fn base() {
+        let page = Url::parse("https://example.com/a/b.html").ok().unwrap();
+        let doc = Html::parse_document(PAGE);
+        assert_eq!(
+            base_url(&doc, &page).as_str(),
+            "https://cdn.example.com/root/"
+        );
+        let doc = Html::parse_document("<html><body><a href='x'>x</a></body></html>");
+        assert_eq!(base_url(&doc, &page).as_str(), page.as_str());
+        let doc = Html::parse_document("<html><head><base href='/other/'></head></html>");
+        assert_eq!(base_url(&doc, &page).as_str(), "https://example.com/other/");
+    }
+
+    macro_rules! link_rel_tests {
+        ($($name:ident: $value:expr,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let (elem, css, js, next, feed): (&str, bool, bool, bool, bool) = $value;
+                let doc = Html::parse_fragment(elem);
+                let link = doc
+                    .root_element()
+                    .descendants()
+                    .filter_map(|d| d.value().as_element())
+                    .find(|e| e.name() == "link")
+                    .unwrap();
+                assert_eq!(is_stylesheet_link(link), css);
+                assert_eq!(is_script_link(link), js);
+                assert_eq!(has_rel(link, "next"), next);
+                assert_eq!(is_feed_link(link), feed);
+            }
+        )*
+        }
+    }
+
+    link_rel_tests! {
+        link_rel_0: (r#"<link href="a.css">"#, false, false, false, false),
+        link_rel_1: (r#"<link rel="stylesheet" href="a.css">"#, true, false, false, false),
+        link_rel_2: (r#"<link rel="alternate  STYLESHEET" href="a.css">"#, true, false, false, false),
+        link_rel_3: (r#"<link rel="preload stylesheet" as="style" href="a.css">"#, true, false, false, false),
+        link_rel_4: (r#"<link rel="preload" as="script" href="a.js">"#, false, true, false, false),
+        link_rel_5: (r#"<link rel="modulepreload" href="a.js">"#, false, true, false, false),
+        link_rel_6: (r#"<link as="script" href="a.js">"#, false, true, false, false),
+        link_rel_7: (r#"<link rel="next" href="/page/2">"#, false, false, true, false),
+        link_rel_8: (r#"<link rel="prev next" href="/page/2">"#, false, false, true, false),
+        link_rel_9: (r#"<link rel="nextpage" href="/page/2">"#, false, false, false, false),
+        link_rel_10: (r#"<link rel="alternate" type="application/rss+xml" href="/feed">"#, false, false, false, true),
+        link_rel_11: (r#"<link rel="Alternate" type="Application/Atom+XML; charset=utf-8" href="/atom">"#, false, false, false, true),
+        link_rel_12: (r#"<link rel="alternate" hreflang="de" href="/de/">"#, false, false, false, false),
+        link_rel_13: (r#"<link type="application/rss+xml" href="/feed">"#, false, false, false, false),
+    }
+
+    #[test]
+    fn css() {
+        let css = r#"@import "base.css";
+@import url('print.css') print;
+body { background: url(/img/bg.png) no-repeat; }
+.a { background-image: URL( "data:image/png;base64,AAAA" ); }
+@font-face { src: url("fonts/a.woff2") format("woff2"), url( fonts/a.ttf ); }
+.b { mask: url(#mask); }"#;
+        assert_eq!(
+            asset_links(css, AssetKind::Css),
+            vec![
+                "base.css",
+                "print.css",
+                "/img/bg.png",
+                "fonts/a.woff2",
+                "fonts/a.ttf",
+            ]
+        );
+    }
+
+    #[test]
+    fn js() {
+        let js = r#"const api = "/api/v1/users";
+const cdn = 'https://cdn.example.com/lib.js';
+const esc = "https:\/\/example.com\/escaped";
+const msg = "hello world", sep = "/", re = "/ x", c = "/* no */";
+const proto = "//example.com/x";
+fetch('data/items.json').then(r => r.json());
+fetch(`/search?q=${q}`);
+xhr.open("GET", "relative/endpoint");
+const t = `/tmpl/${id}`;
+const dup = "/api/v1/users";"#;
+        assert_eq!(
+            asset_links(js, AssetKind::Js),
+            vec![
+                "/api/v1/users",
+                "https://cdn.example.com/lib.js",
+                "https://example.com/escaped",
+                "data/items.json",
+                "relative/endpoint",
+            ]
+        );
+    }
+
+    macro_rules! asset_kind_tests {
+        ($($name:ident: $value:expr,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let (mime, url, exp): (Option<&str>, &str, Option<AssetKind>) = $value;
+                assert_eq!(asset_kind(mime, &Url::parse(url).ok().unwrap()), exp);
+            }
+        )*
+        }
+    }
+
+    asset_kind_tests! {
+        asset_kind_0: (Some("text/css"), "https://example.com/a", Some(AssetKind::Css)),
+        asset_kind_1: (Some("text/javascript"), "https://example.com/a.css", Some(AssetKind::Js)),
+        asset_kind_2: (Some("text/plain"), "https://example.com/a.js", None),
+        asset_kind_3: (None, "https://example.com/a.JS", Some(AssetKind::Js)),
+        asset_kind_4: (None, "https://example.com/m.mjs?v=1", Some(AssetKind::Js)),
+        asset_kind_5: (None, "https://example.com/s.css", Some(AssetKind::Css)),
+        asset_kind_6: (None, "https://example.com/css/", None),
+    }
+
+    #[test]
+    fn text_assets() {
+        for (url, exp) in [
+            ("https://example.com/a.css", true),
+            ("https://example.com/fonts/a.WOFF2", false),
+            ("https://example.com/img/a.png?v=2", false),
+            ("https://example.com/api/users", true),
+        ] {
+            assert_eq!(
+                is_text_asset(&Url::parse(url).ok().unwrap()),
+                exp,
+                "{}",
+                url
+            );
+        }
+    }
+
+    macro_rules! refresh_tests {
+        ($($name:ident: $value:expr,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let (prov, exp): (&str, Option<&str>) = $value;
+                assert_eq!(refresh_url(prov), exp);
+            }
+        )*
+        }
+    }
+
+    refresh_tests! {
+        refresh_0: ("0; url=/next", Some("/next")),
+        refresh_1: ("5;URL = 'https://example.com/'", Some("https://example.com/")),
+        refresh_2: ("3, url=\"/quoted\"", Some("/quoted")),
+        refresh_3: ("10", None),
+        refresh_4: ("0; /next", None),
+        refresh_5: ("0; ünï=/next", None),
+    }
+}
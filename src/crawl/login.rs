@@ -0,0 +1,368 @@
+use log::{debug, info};
+use regex::Regex;
+use reqwest::header::{CONTENT_TYPE, REFERER};
+use reqwest::{Client, Url};
+use scraper::{ElementRef, Html, Selector};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::error::Error;
+
+use super::{base_url, StatusRanges};
+
+/// Form fields submitted by a login, along with where and how to submit them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormSubmission {
+    /// Url the form is submitted to.
+    pub action: Url,
+    /// Whether the form is submitted via GET, rather than POST.
+    pub get: bool,
+    /// Names and values of the fields, in document order.
+    pub fields: Vec<(String, String)>,
+}
+
+/// A login performed by submitting a form; the form page is fetched, the
+/// form's fields (including hidden fields, such as CSRF tokens) are filled in
+/// with the configured values, and the form is submitted.
+#[derive(Debug, Clone)]
+pub struct LoginForm {
+    /// Url of the page holding the login form.
+    url: Url,
+    /// Values of the fields to fill in, by name.
+    fields: Vec<(String, String)>,
+    /// Css selector of the login form; defaults to the first form holding a
+    /// password field.
+    selector: Option<String>,
+    /// Pattern the response to a successful login must match.
+    success: Option<Regex>,
+    /// Status codes of the response to a successful login.
+    success_status: StatusRanges,
+    /// Pattern marking pages served once logged out.
+    logged_out: Option<Regex>,
+    /// Number of successful logins, shared among clones.
+    logins: Arc<AtomicUsize>,
+    /// Held while logging in, so that concurrent logins are not attempted.
+    lock: Arc<Mutex<()>>,
+}
+
+impl LoginForm {
+    /// Returns a new LoginForm instance, filling in the given fields of the
+    /// form found at the given url.
+    pub fn new(url: Url, fields: Vec<(String, String)>) -> Self {
+        Self {
+            url,
+            fields,
+            selector: None,
+            success: None,
+            success_status: StatusRanges::default(),
+            logged_out: None,
+            logins: Arc::new(AtomicUsize::new(0)),
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Returns the url of the page holding the login form.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Set the css selector of the login form.
+    pub fn set_selector(&mut self, selector: &str) -> Result<(), Error> {
+        parse_selector(selector)?;
+        self.selector = Some(selector.to_string());
+        Ok(())
+    }
+
+    /// Set the pattern the response to a successful login must match.
+    pub fn set_success(&mut self, success: Regex) {
+        self.success = Some(success);
+    }
+
+    /// Set the status codes of the response to a successful login.
+    pub fn set_success_status(&mut self, success_status: StatusRanges) {
+        self.success_status = success_status;
+    }
+
+    /// Set the pattern marking pages served once logged out.
+    pub fn set_logged_out(&mut self, logged_out: Regex) {
+        self.logged_out = Some(logged_out);
+    }
+
+    /// Returns whether or not the given page text shows that the session was
+    /// logged out.
+    pub fn is_logged_out(&self, text: &str) -> bool {
+        self.logged_out.as_ref().is_some_and(|r| r.is_match(text))
+    }
+
+    /// Returns the number of successful logins so far.
+    pub fn logins(&self) -> usize {
+        self.logins.load(Ordering::SeqCst)
+    }
+
+    /// Log in, returning an error if the login was not successful.
+    pub async fn login(&self, client: &Client) -> Result<(), Error> {
+        let _guard = self.lock.lock().await;
+        self.submit(client).await
+    }
+
+    /// Log in again, unless another login succeeded since the given number
+    /// of logins was observed (i.e. the session was already renewed).
+    pub async fn relogin(&self, client: &Client, seen: usize) -> Result<(), Error> {
+        let _guard = self.lock.lock().await;
+        if self.logins() != seen {
+            return Ok(());
+        }
+        self.submit(client).await
+    }
+
+    /// Fetch the login form, then fill in and submit it.
+    async fn submit(&self, client: &Client) -> Result<(), Error> {
+        debug!("fetching login form: {}", self.url);
+        let page = client.get(self.url.clone()).send().await?;
+        let page_url = page.url().clone();
+        let body = page.text().await?;
+        let form = self.fill(&body, &page_url)?;
+
+        let encoded = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(form.fields.iter())
+            .finish();
+        let request = if form.get {
+            let mut action = form.action.clone();
+            action.set_query(Some(&encoded));
+            client.get(action)
+        } else {
+            client
+                .post(form.action.clone())
+                .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+                .body(encoded)
+        };
+        debug!("submitting login form to: {}", form.action);
+        let response = request.header(REFERER, page_url.as_str()).send().await?;
+        let status = response.status();
+        let text = response.text().await?;
+
+        if !self.success_status.contains(status) {
+            return Err(Error::LoginError(format!(
+                "unexpected status code: {}",
+                status
+            )));
+        }
+        if let Some(success) = &self.success {
+            if !success.is_match(&text) {
+                return Err(Error::LoginError(format!(
+                    "response did not match '{}'",
+                    success.as_str()
+                )));
+            }
+        }
+        let logins = self.logins.fetch_add(1, Ordering::SeqCst) + 1;
+        info!("logged in via {} (login #{})", self.url, logins);
+        Ok(())
+    }
+
+    /// Returns the submission of the login form found in the given page,
+    /// filled in with the configured field values.
+    pub fn fill(&self, body: &str, page: &Url) -> Result<FormSubmission, Error> {
+        let doc = Html::parse_document(body);
+        let form = match &self.selector {
+            Some(s) => doc
+                .select(&parse_selector(s)?)
+                .find(|e| e.value().name() == "form")
+                .ok_or(Error::LoginError(format!("no form matching '{}'", s)))?,
+            None => {
+                let password = parse_selector("input[type=password i]")?;
+                doc.select(&parse_selector("form")?)
+                    .find(|f| f.select(&password).next().is_some())
+                    .ok_or(Error::LoginError(
+                        "no form with a password field".to_string(),
+                    ))?
+            }
+        };
+
+        let mut fields = form_fields(form);
+        for (name, value) in self.fields.iter() {
+            match fields.iter_mut().find(|(n, _)| n == name) {
+                Some(field) => field.1 = value.clone(),
+                None => fields.push((name.clone(), value.clone())),
+            }
+        }
+
+        let base = base_url(&doc, page);
+        let action = match form.value().attr("action").map(|a| a.trim()) {
+            Some(a) if !a.is_empty() => base.join(a)?,
+            _ => page.clone(),
+        };
+        let get = form
+            .value()
+            .attr("method")
+            .is_none_or(|m| m.trim().eq_ignore_ascii_case("get"));
+        Ok(FormSubmission {
+            action,
+            get,
+            fields,
+        })
+    }
+}
+
+/// Returns the successful (i.e. submitted) controls of a form, as a browser
+/// would when submitting it via its first submit button.
+fn form_fields(form: ElementRef) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+    let mut submitter = false;
+    for elem in form.descendants().filter_map(ElementRef::wrap) {
+        let e = elem.value();
+        let name = match e.attr("name") {
+            Some(n) if !n.is_empty() && e.attr("disabled").is_none() => n.to_string(),
+            _ => continue,
+        };
+        match e.name() {
+            "input" => {
+                let kind = e.attr("type").unwrap_or("text").to_lowercase();
+                let value = e.attr("value").unwrap_or("").to_string();
+                match kind.as_str() {
+                    "submit" if !submitter => {
+                        submitter = true;
+                        fields.push((name, value));
+                    }
+                    "checkbox" | "radio" if e.attr("checked").is_some() => {
+                        let value = e.attr("value").unwrap_or("on").to_string();
+                        fields.push((name, value));
+                    }
+                    "submit" | "checkbox" | "radio" | "image" | "button" | "reset" | "file" => {}
+                    _ => fields.push((name, value)),
+                }
+            }
+            "button" => {
+                let kind = e.attr("type").unwrap_or("submit").to_lowercase();
+                if kind == "submit" && !submitter {
+                    submitter = true;
+                    fields.push((name, e.attr("value").unwrap_or("").to_string()));
+                }
+            }
+            "select" => {
+                let options: Vec<ElementRef> = elem
+                    .descendants()
+                    .filter_map(ElementRef::wrap)
+                    .filter(|o| o.value().name() == "option")
+                    .collect();
+                let selected = options
+                    .iter()
+                    .find(|o| o.value().attr("selected").is_some())
+                    .or(options.first());
+                if let Some(o) = selected {
+                    let value = match o.value().attr("value") {
+                        Some(v) => v.to_string(),
+                        None => o.text().collect::<String>().trim().to_string(),
+                    };
+                    fields.push((name, value));
+                }
+            }
+            "textarea" => fields.push((name, elem.text().collect())),
+            _ => {}
+        }
+    }
+    fields
+}
+
+/// Parses the given css selector.
+pub fn parse_selector(selector: &str) -> Result<Selector, Error> {
+    Selector::parse(selector)
+        .map_err(|e| Error::GeneralError(format!("invalid selector '{}': {}", selector, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r#"<html><head><base href="/app/"></head><body>
+<form action="/search" method="get"><input name="q"></form>
+<form id="login" action="session" method="POST">
+  <input type="hidden" name="csrf_token" value="t0k3n">
+  <input type="text" name="username" value="">
+  <input type="password" name="password">
+  <input type="checkbox" name="remember" checked>
+  <input type="checkbox" name="newsletter" value="yes">
+  <input type="text" name="disabled" value="x" disabled>
+  <select name="realm"><option value="a">A</option><option value="b" selected>B</option></select>
+  <textarea name="note">hi</textarea>
+  <input type="submit" name="commit" value="Sign in">
+  <button type="submit" name="other" value="no">Other</button>
+</form>
+</body></html>"#;
+
+    fn login(fields: &[(&str, &str)]) -> LoginForm {
+        LoginForm::new(
+            Url::parse("https://example.com/login").ok().unwrap(),
+            fields
+                .iter()
+                .map(|(n, v)| (n.to_string(), v.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn fill() {
+        let form = login(&[
+            ("username", "alice"),
+            ("password", "hunter2"),
+            ("otp", "123"),
+        ]);
+        let page = Url::parse("https://example.com/login").ok().unwrap();
+        let sub = form.fill(PAGE, &page).ok().unwrap();
+        assert_eq!(sub.action.as_str(), "https://example.com/app/session");
+        assert!(!sub.get);
+        let exp: Vec<(String, String)> = [
+            ("csrf_token", "t0k3n"),
+            ("username", "alice"),
+            ("password", "hunter2"),
+            ("remember", "on"),
+            ("realm", "b"),
+            ("note", "hi"),
+            ("commit", "Sign in"),
+            ("otp", "123"),
+        ]
+        .iter()
+        .map(|(n, v)| (n.to_string(), v.to_string()))
+        .collect();
+        assert_eq!(sub.fields, exp);
+    }
+
+    #[test]
+    fn fill_selector() {
+        let mut form = login(&[("q", "words")]);
+        form.set_selector("form[action='/search']").ok().unwrap();
+        let page = Url::parse("https://example.com/login").ok().unwrap();
+        let sub = form.fill(PAGE, &page).ok().unwrap();
+        assert_eq!(sub.action.as_str(), "https://example.com/search");
+        assert!(sub.get);
+        assert_eq!(sub.fields, vec![("q".to_string(), "words".to_string())]);
+
+        form.set_selector("#missing").ok().unwrap();
+        assert!(form.fill(PAGE, &page).is_err());
+        assert!(form.set_selector("form[").is_err());
+    }
+
+    #[test]
+    fn fill_no_form() {
+        let form = login(&[]);
+        let page = Url::parse("https://example.com/login").ok().unwrap();
+        assert!(form
+            .fill(r#"<form><input name="q"></form>"#, &page)
+            .is_err());
+        let sub = form
+            .fill(r#"<form><input type="PASSWORD" name="pw"></form>"#, &page)
+            .ok()
+            .unwrap();
+        assert_eq!(sub.action, page);
+    }
+
+    #[test]
+    fn logged_out() {
+        let mut form = login(&[]);
+        assert!(!form.is_logged_out("Please sign in"));
+        form.set_logged_out(Regex::new("(?i)please sign in").ok().unwrap());
+        assert!(form.is_logged_out("<p>Please Sign In</p>"));
+        assert!(!form.is_logged_out("Welcome back"));
+    }
+}
@@ -1,5 +1,33 @@
+mod auth;
 mod crawler;
+mod har;
+mod limits;
+mod links;
+mod login;
+mod normalize;
+mod proxy;
+mod retry;
+mod robots;
+mod scope;
 mod site;
+mod sitemap;
+mod status;
+mod tls;
+mod warc;
 
+pub use self::auth::*;
 pub use self::crawler::*;
+pub use self::har::*;
+pub use self::limits::*;
+pub use self::links::*;
+pub use self::login::*;
+pub use self::normalize::*;
+pub use self::proxy::*;
+pub use self::retry::*;
+pub use self::robots::*;
+pub use self::scope::*;
 pub use self::site::*;
+pub use self::sitemap::*;
+pub use self::status::*;
+pub use self::tls::*;
+pub use self::warc::*;
@@ -0,0 +1,122 @@
+use reqwest::Url;
+
+/// Query parameters commonly used for tracking, which do not change the
+/// content of a page; a trailing `*` matches any parameter with that prefix.
+pub const DEFAULT_TRACKING_PARAMS: [&str; 10] = [
+    "utm_*", "gclid", "fbclid", "msclkid", "dclid", "yclid", "mc_cid", "mc_eid", "_hsenc", "_hsmi",
+];
+
+/// Normalizes urls, so that different spellings of the same url are only
+/// crawled once.
+#[derive(Debug, Clone)]
+pub struct UrlNormalizer {
+    /// Query parameters to remove.
+    strip_params: Vec<String>,
+    /// Sort query parameters.
+    sort_query: bool,
+    /// Remove trailing slashes from paths.
+    fold_trailing_slash: bool,
+}
+
+impl Default for UrlNormalizer {
+    /// Returns a normalizer that strips common tracking parameters and sorts
+    /// query parameters, leaving trailing slashes in place.
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_TRACKING_PARAMS
+                .iter()
+                .map(|p| p.to_string())
+                .collect(),
+            true,
+            false,
+        )
+    }
+}
+
+impl UrlNormalizer {
+    /// Returns a new UrlNormalizer instance.
+    pub fn new(strip_params: Vec<String>, sort_query: bool, fold_trailing_slash: bool) -> Self {
+        Self {
+            strip_params: strip_params
+                .into_iter()
+                .map(|p| p.trim().to_lowercase())
+                .filter(|p| !p.is_empty())
+                .collect(),
+            sort_query,
+            fold_trailing_slash,
+        }
+    }
+
+    /// Returns the normalized form of the given url; the fragment is removed,
+    /// along with configured query parameters, and the query is optionally
+    /// sorted and trailing slash optionally removed. Scheme and host case, as
+    /// well as default ports, are already normalized when parsing a `Url`.
+    pub fn normalize(&self, url: &Url) -> Url {
+        let mut url = url.clone();
+        url.set_fragment(None);
+
+        if let Some(query) = url.query() {
+            let mut params: Vec<&str> = query
+                .split('&')
+                .filter(|p| !p.is_empty())
+                .filter(|p| !self.is_stripped(p.split('=').next().unwrap_or("")))
+                .collect();
+            if self.sort_query {
+                params.sort_unstable();
+            }
+            let query = params.join("&");
+            if query.is_empty() {
+                url.set_query(None);
+            } else {
+                url.set_query(Some(&query));
+            }
+        }
+
+        if self.fold_trailing_slash && url.path().len() > 1 && url.path().ends_with('/') {
+            let path = url.path().trim_end_matches('/').to_string();
+            url.set_path(if path.is_empty() { "/" } else { &path });
+        }
+        url
+    }
+
+    /// Returns whether or not the query parameter of the given name is removed.
+    fn is_stripped(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.strip_params.iter().any(|p| match p.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == *p,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! normalize_tests {
+        ($($name:ident: $value:expr,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let (normalizer, prov, exp): (UrlNormalizer, &str, &str) = $value;
+                let url = Url::parse(prov).ok().unwrap();
+                assert_eq!(normalizer.normalize(&url).as_str(), exp);
+            }
+        )*
+        }
+    }
+
+    normalize_tests! {
+        normalize_0: (UrlNormalizer::default(), "HTTP://Example.COM:80/page", "http://example.com/page"),
+        normalize_1: (UrlNormalizer::default(), "https://example.com:443/page#frag", "https://example.com/page"),
+        normalize_2: (UrlNormalizer::default(), "https://example.com/page?utm_source=x&utm_medium=y", "https://example.com/page"),
+        normalize_3: (UrlNormalizer::default(), "https://example.com/page?b=2&a=1&fbclid=z", "https://example.com/page?a=1&b=2"),
+        normalize_4: (UrlNormalizer::default(), "https://example.com/page/", "https://example.com/page/"),
+        normalize_5: (UrlNormalizer::default(), "https://example.com/?&&q=a%20b&", "https://example.com/?q=a%20b"),
+        normalize_6: (UrlNormalizer::default(), "https://example.com:8080/", "https://example.com:8080/"),
+        normalize_7: (UrlNormalizer::new(vec![], false, true), "https://example.com/page/?b=2&a=1&utm_id=3", "https://example.com/page?b=2&a=1&utm_id=3"),
+        normalize_8: (UrlNormalizer::new(vec![], false, true), "https://example.com/", "https://example.com/"),
+        normalize_9: (UrlNormalizer::new(vec![], false, true), "https://example.com//", "https://example.com/"),
+        normalize_10: (UrlNormalizer::new(vec!["SID".to_string(), "".to_string()], true, false), "https://example.com/a?sid=1&x=2", "https://example.com/a?x=2"),
+    }
+}
@@ -0,0 +1,152 @@
+use reqwest::StatusCode;
+use std::time::{Duration, SystemTime};
+
+use crate::utils;
+
+/// Policy for retrying requests that were throttled or hit a server error.
+#[derive(Copy, Debug, Clone)]
+pub struct RetryPolicy {
+    /// Maximum number of retries for a single request.
+    max_retries: usize,
+    /// Delay before the first retry; doubled for each subsequent retry.
+    base_delay: Duration,
+    /// Upper limit on the delay between retries.
+    max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    /// Returns a policy of 3 retries, backing off from 500ms up to 60s.
+    fn default() -> Self {
+        Self::new(3, Duration::from_millis(500), Duration::from_secs(60))
+    }
+}
+
+impl RetryPolicy {
+    /// Returns a new RetryPolicy instance.
+    pub fn new(max_retries: usize, base_delay: Duration, max_delay: Duration) -> Self {
+        Self {
+            max_retries,
+            base_delay,
+            max_delay,
+        }
+    }
+
+    /// Returns the maximum number of retries for a single request.
+    pub fn max_retries(&self) -> usize {
+        self.max_retries
+    }
+
+    /// Returns whether or not a response with the given status code should be retried.
+    pub fn is_retryable(status: StatusCode) -> bool {
+        matches!(status.as_u16(), 429 | 500 | 502 | 503 | 504)
+    }
+
+    /// Returns whether or not a response with the given status code indicates
+    /// the host is throttling us.
+    pub fn is_throttled(status: StatusCode) -> bool {
+        matches!(status.as_u16(), 429 | 503)
+    }
+
+    /// Returns how long to wait before the given retry attempt (starting at 1);
+    /// exponential backoff with jitter, extended to honor the server's
+    /// requested delay, and capped at the configured maximum.
+    pub fn delay(&self, attempt: usize, retry_after: Option<Duration>) -> Duration {
+        let exp = attempt.saturating_sub(1).min(16) as u32;
+        let backoff = self
+            .base_delay
+            .saturating_mul(2u32.pow(exp))
+            .min(self.max_delay);
+
+        // "equal jitter"; wait at least half of the backoff
+        let half = (backoff.as_millis() / 2).min(u128::from(u32::MAX)) as u32;
+        let jitter = if half > 0 {
+            utils::num_between(0, half)
+        } else {
+            0
+        };
+        let jittered = Duration::from_millis(u64::from(half) + u64::from(jitter));
+
+        match retry_after {
+            Some(ra) if ra > jittered => ra.min(self.max_delay),
+            _ => jittered,
+        }
+    }
+}
+
+/// Parses the value of a `Retry-After` header, given either as a number of
+/// seconds or as an HTTP-date.
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    match httpdate::parse_http_date(value) {
+        Ok(when) => Some(
+            when.duration_since(SystemTime::now())
+                .unwrap_or(Duration::ZERO),
+        ),
+        Err(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! retry_after_tests {
+        ($($name:ident: $value:expr,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let (prov, exp) = $value;
+                assert_eq!(parse_retry_after(prov), exp);
+            }
+        )*
+        }
+    }
+
+    retry_after_tests! {
+        retry_after_0: ("120", Some(Duration::from_secs(120))),
+        retry_after_1: (" 0 ", Some(Duration::ZERO)),
+        retry_after_2: ("Wed, 21 Oct 2015 07:28:00 GMT", Some(Duration::ZERO)),
+        retry_after_3: ("soon", None),
+        retry_after_4: ("-1", None),
+        retry_after_5: ("", None),
+    }
+
+    #[test]
+    fn retry_after_future_date() {
+        let when = SystemTime::now() + Duration::from_secs(3600);
+        let parsed = parse_retry_after(&httpdate::fmt_http_date(when)).unwrap();
+        assert!(parsed > Duration::from_secs(3590) && parsed <= Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn delay_backs_off() {
+        let policy = RetryPolicy::new(5, Duration::from_millis(100), Duration::from_secs(1));
+        for (attempt, max) in [(1, 100), (2, 200), (3, 400), (4, 800), (5, 1000), (9, 1000)] {
+            let d = policy.delay(attempt, None);
+            assert!(
+                d >= Duration::from_millis(max / 2),
+                "attempt {}: {:?}",
+                attempt,
+                d
+            );
+            assert!(
+                d <= Duration::from_millis(max),
+                "attempt {}: {:?}",
+                attempt,
+                d
+            );
+        }
+    }
+
+    #[test]
+    fn delay_honors_retry_after() {
+        let policy = RetryPolicy::new(5, Duration::from_millis(100), Duration::from_secs(10));
+        let d = policy.delay(1, Some(Duration::from_secs(5)));
+        assert_eq!(d, Duration::from_secs(5));
+        let d = policy.delay(1, Some(Duration::from_secs(30)));
+        assert_eq!(d, Duration::from_secs(10));
+    }
+}
@@ -0,0 +1,380 @@
+use reqwest::Url;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+
+/// Product token used to select robots.txt groups when no user agent is configured.
+pub const DEFAULT_ROBOTS_AGENT: &str = "wdict";
+
+/// Rules parsed from a robots.txt file that apply to a single user agent.
+#[derive(Debug, Clone, Default)]
+pub struct Robots {
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+    sitemaps: Vec<String>,
+    /// When the rules should be fetched again; only set for a robots.txt
+    /// that could not be fetched.
+    expires: Option<Instant>,
+}
+
+/// A single allow/disallow rule.
+#[derive(Debug, Clone)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+/// A group of rules, as introduced by one or more user-agent lines.
+#[derive(Debug, Default)]
+struct Group {
+    agents: Vec<String>,
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+}
+
+impl Robots {
+    /// Returns rules that allow visiting every url.
+    pub fn allow_all() -> Self {
+        Self::default()
+    }
+
+    /// Returns rules that disallow visiting every url.
+    pub fn disallow_all() -> Self {
+        Self {
+            rules: vec![Rule {
+                allow: false,
+                pattern: "/".to_string(),
+            }],
+            ..Self::default()
+        }
+    }
+
+    /// Returns rules that disallow visiting every url for the given time, as
+    /// for a robots.txt that could not be fetched; once expired, the rules
+    /// are to be fetched again.
+    pub fn unreachable(ttl: Duration) -> Self {
+        Self {
+            expires: Some(Instant::now() + ttl),
+            ..Self::disallow_all()
+        }
+    }
+
+    /// Returns whether or not the rules stand in for a robots.txt that could
+    /// not be fetched.
+    pub fn is_unreachable(&self) -> bool {
+        self.expires.is_some()
+    }
+
+    /// Returns whether or not the rules are to be fetched again.
+    pub fn is_expired(&self) -> bool {
+        self.expires.is_some_and(|e| Instant::now() >= e)
+    }
+
+    /// Parses the contents of a robots.txt file, keeping only the rules of the
+    /// group(s) that best match the given user agent; falls back to the `*`
+    /// group(s), then to allowing everything.
+    pub fn parse(content: &str, user_agent: &str) -> Self {
+        let token = agent_token(user_agent);
+        let mut groups: Vec<Group> = Vec::new();
+        let mut sitemaps = Vec::new();
+        // consecutive user-agent lines share a single group
+        let mut reading_agents = false;
+
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let (key, val) = match line.split_once(':') {
+                Some((k, v)) => (k.trim().to_lowercase(), v.trim()),
+                None => continue,
+            };
+            match key.as_str() {
+                "user-agent" => {
+                    if !reading_agents {
+                        groups.push(Group::default());
+                    }
+                    if let Some(g) = groups.last_mut() {
+                        g.agents.push(val.to_lowercase());
+                    }
+                    reading_agents = true;
+                }
+                "allow" | "disallow" => {
+                    reading_agents = false;
+                    // an empty disallow means allow everything; no rule needed
+                    if val.is_empty() {
+                        continue;
+                    }
+                    if let Some(g) = groups.last_mut() {
+                        g.rules.push(Rule {
+                            allow: key == "allow",
+                            pattern: val.to_string(),
+                        });
+                    }
+                }
+                "crawl-delay" => {
+                    reading_agents = false;
+                    if let Some(g) = groups.last_mut() {
+                        if let Ok(secs) = val.parse::<f64>() {
+                            if secs.is_finite() && secs > 0.0 {
+                                g.crawl_delay = Some(Duration::from_secs_f64(secs));
+                            }
+                        }
+                    }
+                }
+                // sitemaps are not tied to any group
+                "sitemap" if !val.is_empty() => {
+                    sitemaps.push(val.to_string());
+                }
+                _ => {}
+            }
+        }
+
+        let specific: Vec<&Group> = groups
+            .iter()
+            .filter(|g| g.agents.contains(&token))
+            .collect();
+        let selected = if specific.is_empty() {
+            groups
+                .iter()
+                .filter(|g| g.agents.iter().any(|a| a == "*"))
+                .collect()
+        } else {
+            specific
+        };
+
+        let mut robots = Self {
+            sitemaps,
+            ..Self::default()
+        };
+        for g in selected {
+            robots.rules.extend(g.rules.iter().cloned());
+            if g.crawl_delay.is_some() {
+                robots.crawl_delay = g.crawl_delay;
+            }
+        }
+        robots
+    }
+
+    /// Returns whether or not the given url may be visited; the longest
+    /// matching rule wins, and allow wins a tie.
+    pub fn is_allowed(&self, url: &Url) -> bool {
+        let mut target = url.path().to_string();
+        if let Some(q) = url.query() {
+            target.push('?');
+            target.push_str(q);
+        }
+
+        let mut best: Option<(usize, bool)> = None;
+        for rule in self.rules.iter() {
+            if !pattern_matches(&rule.pattern, &target) {
+                continue;
+            }
+            let len = rule.pattern.len();
+            match best {
+                Some((l, allow)) if l > len || (l == len && allow) => {}
+                _ => best = Some((len, rule.allow)),
+            }
+        }
+
+        match best {
+            Some((_, allow)) => allow,
+            None => true,
+        }
+    }
+
+    /// Returns the crawl delay requested for our user agent, if any.
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+
+    /// Returns an iterator over the sitemap urls listed in the file.
+    pub fn sitemaps(&self) -> impl Iterator<Item = &String> {
+        self.sitemaps.iter()
+    }
+}
+
+/// Returns the robots.txt url for the host of the given url.
+pub fn robots_url(url: &Url) -> Option<Url> {
+    url.join("/robots.txt").ok()
+}
+
+/// Returns the lowercase product token of a user agent string (e.g. `foo`
+/// from `Foo/1.0 (bar)`).
+fn agent_token(user_agent: &str) -> String {
+    let token = user_agent
+        .split(|c: char| c == '/' || c.is_whitespace())
+        .next()
+        .unwrap_or("")
+        .to_lowercase();
+    if token.is_empty() {
+        DEFAULT_ROBOTS_AGENT.to_string()
+    } else {
+        token
+    }
+}
+
+/// Returns whether or not the given robots.txt path pattern matches the
+/// target; supports `*` wildcards and a trailing `$` anchor.
+fn pattern_matches(pattern: &str, target: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let last = parts.len() - 1;
+    let mut pos = 0;
+    for (i, part) in parts.iter().enumerate() {
+        if i == 0 {
+            if !target.starts_with(part) {
+                return false;
+            }
+            pos = part.len();
+        } else if i == last && anchored {
+            return target.len() >= pos + part.len() && target.ends_with(part);
+        } else {
+            match target[pos..].find(part) {
+                Some(idx) => pos += idx + part.len(),
+                None => return false,
+            }
+        }
+    }
+
+    !anchored || pos == target.len()
+}
+
+/// Caches robots.txt rules, keyed by url origin.
+#[derive(Debug)]
+pub struct RobotsDb(Arc<Mutex<HashMap<String, Arc<OnceCell<Robots>>>>>);
+
+impl Clone for RobotsDb {
+    /// Returns a clone/handle of the given RobotsDb.
+    fn clone(&self) -> Self {
+        RobotsDb(Arc::clone(&self.0))
+    }
+}
+
+impl Default for RobotsDb {
+    /// Returns a new, empty RobotsDb.
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RobotsDb {
+    /// Returns a new RobotsDb instance.
+    pub fn new() -> Self {
+        RobotsDb(Arc::new(Mutex::new(HashMap::new())))
+    }
+
+    /// Returns the cell holding rules for the origin of the given url; the
+    /// cell remains empty until the rules are fetched, and is replaced by an
+    /// empty one once its rules have expired.
+    pub fn cell(&self, url: &Url) -> Arc<OnceCell<Robots>> {
+        let mut hm: MutexGuard<HashMap<String, Arc<OnceCell<Robots>>>> = match self.0.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let key = url.origin().ascii_serialization();
+        match hm.get(&key) {
+            Some(cell) if !cell.get().is_some_and(|r| r.is_expired()) => cell.clone(),
+            _ => {
+                let cell = Arc::new(OnceCell::new());
+                hm.insert(key, cell.clone());
+                cell
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOTS: &str = "
+# comment line
+User-agent: *
+Disallow: /private/
+Allow: /private/public.html
+Disallow: /*.pdf$
+Disallow: /search?
+Crawl-delay: 2
+
+User-agent: wdict
+User-agent: otherbot
+Disallow: /nowdict
+Disallow:
+
+Sitemap: https://example.com/sitemap.xml
+";
+
+    macro_rules! robots_tests {
+        ($($name:ident: $value:expr,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let (agent, url, result) = $value;
+                let robots = Robots::parse(ROBOTS, agent);
+                assert_eq!(robots.is_allowed(&Url::parse(url).ok().unwrap()), result);
+            }
+        )*
+        }
+    }
+
+    robots_tests! {
+        star_0: ("somebot/1.0", "https://example.com/", true),
+        star_1: ("somebot/1.0", "https://example.com/private/", false),
+        star_2: ("somebot/1.0", "https://example.com/private/a/b", false),
+        star_3: ("somebot/1.0", "https://example.com/private/public.html", true),
+        star_4: ("somebot/1.0", "https://example.com/docs/a.pdf", false),
+        star_5: ("somebot/1.0", "https://example.com/docs/a.pdf?x=y", true),
+        star_6: ("somebot/1.0", "https://example.com/search?q=a", false),
+        star_7: ("somebot/1.0", "https://example.com/search", true),
+        star_8: ("somebot/1.0", "https://example.com/nowdict", true),
+
+        agent_0: ("wdict", "https://example.com/private/", true),
+        agent_1: ("wdict", "https://example.com/nowdict", false),
+        agent_2: ("Wdict/0.1.22", "https://example.com/nowdict/a", false),
+        agent_3: ("otherbot", "https://example.com/docs/a.pdf", true),
+    }
+
+    #[test]
+    fn crawl_delay() {
+        let robots = Robots::parse(ROBOTS, "somebot");
+        assert_eq!(robots.crawl_delay(), Some(Duration::from_secs(2)));
+        let robots = Robots::parse(ROBOTS, "wdict");
+        assert_eq!(robots.crawl_delay(), None);
+    }
+
+    #[test]
+    fn sitemaps() {
+        let robots = Robots::parse(ROBOTS, "wdict");
+        let sitemaps: Vec<&String> = robots.sitemaps().collect();
+        assert_eq!(sitemaps, vec!["https://example.com/sitemap.xml"]);
+    }
+
+    #[test]
+    fn disallow_all() {
+        let robots = Robots::disallow_all();
+        assert!(!robots.is_allowed(&Url::parse("https://example.com/").ok().unwrap()));
+    }
+
+    #[test]
+    fn unreachable() {
+        let url = Url::parse("https://example.com/").ok().unwrap();
+        let robots = Robots::unreachable(Duration::from_secs(60));
+        assert!(robots.is_unreachable());
+        assert!(!robots.is_expired());
+        assert!(!robots.is_allowed(&url));
+        assert!(!Robots::disallow_all().is_unreachable());
+
+        // expired rules are replaced by an empty cell, to be fetched again
+        let db = RobotsDb::new();
+        assert!(db
+            .cell(&url)
+            .set(Robots::unreachable(Duration::ZERO))
+            .is_ok());
+        assert!(db.cell(&url).get().is_none());
+        assert!(db.cell(&url).set(Robots::allow_all()).is_ok());
+        assert!(db.cell(&url).get().is_some());
+    }
+}
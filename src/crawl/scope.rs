@@ -0,0 +1,153 @@
+use regex::Regex;
+use reqwest::Url;
+
+use crate::error::Error;
+
+/// A pattern matched against the path and query of a url (e.g. `/docs/a?x=1`).
+#[derive(Debug, Clone)]
+pub struct UrlPattern {
+    /// Pattern as provided.
+    pattern: String,
+    /// Compiled form of the pattern.
+    regex: Regex,
+}
+
+impl UrlPattern {
+    /// Returns a new UrlPattern from a regular expression; the expression may
+    /// match any part of the path and query, unless anchored.
+    pub fn regex(pattern: &str) -> Result<Self, Error> {
+        Ok(Self {
+            pattern: pattern.to_string(),
+            regex: Regex::new(pattern)?,
+        })
+    }
+
+    /// Returns a new UrlPattern from a glob; the glob must match the entire
+    /// path and query, where `**` matches anything, `*` matches anything but
+    /// `/`, and `?` matches any single character but `/`.
+    pub fn glob(pattern: &str) -> Result<Self, Error> {
+        let mut re = String::from("^");
+        let mut chars = pattern.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    re.push_str(".*");
+                }
+                '*' => re.push_str("[^/]*"),
+                '?' => re.push_str("[^/]"),
+                c => re.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        re.push('$');
+        Ok(Self {
+            pattern: pattern.to_string(),
+            regex: Regex::new(&re)?,
+        })
+    }
+
+    /// Returns the pattern as provided.
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Returns whether or not the path and query of the given url match the pattern.
+    pub fn matches(&self, url: &Url) -> bool {
+        self.regex.is_match(&path_and_query(url))
+    }
+}
+
+/// Returns the path and query of the given url.
+fn path_and_query(url: &Url) -> String {
+    match url.query() {
+        Some(q) => format!("{}?{}", url.path(), q),
+        None => url.path().to_string(),
+    }
+}
+
+/// Include and exclude rules limiting which urls are crawled.
+#[derive(Debug, Clone, Default)]
+pub struct UrlScope {
+    /// If any are given, urls must match at least one.
+    include: Vec<UrlPattern>,
+    /// Urls must not match any.
+    exclude: Vec<UrlPattern>,
+}
+
+impl UrlScope {
+    /// Returns a new UrlScope instance.
+    pub fn new(include: Vec<UrlPattern>, exclude: Vec<UrlPattern>) -> Self {
+        Self { include, exclude }
+    }
+
+    /// Returns whether or not the given url is within scope; exclusions take
+    /// precedence over inclusions.
+    pub fn matches(&self, url: &Url) -> bool {
+        if self.exclude.iter().any(|p| p.matches(url)) {
+            return false;
+        }
+        self.include.is_empty() || self.include.iter().any(|p| p.matches(url))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! glob_tests {
+        ($($name:ident: $value:expr,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let (glob, url, exp): (&str, &str, bool) = $value;
+                let pattern = UrlPattern::glob(glob).ok().unwrap();
+                assert_eq!(pattern.matches(&Url::parse(url).ok().unwrap()), exp);
+            }
+        )*
+        }
+    }
+
+    glob_tests! {
+        glob_0: ("/docs/**", "https://example.com/docs/a/b.html", true),
+        glob_1: ("/docs/**", "https://example.com/blog/docs/a", false),
+        glob_2: ("/docs/*", "https://example.com/docs/a/b.html", false),
+        glob_3: ("/docs/*", "https://example.com/docs/a.html", true),
+        glob_4: ("**.pdf", "https://example.com/files/report.pdf", true),
+        glob_5: ("**.pdf", "https://example.com/files/report.pdf.html", false),
+        glob_6: ("/page?.html", "https://example.com/page1.html", true),
+        glob_7: ("/search?q=*", "https://example.com/search?q=x", true),
+        glob_8: ("/calendar/**", "https://example.com/calendar/2024/01?view=day", true),
+    }
+
+    macro_rules! scope_tests {
+        ($($name:ident: $value:expr,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let (include, exclude, url, exp): (Vec<&str>, Vec<&str>, &str, bool) = $value;
+                let scope = UrlScope::new(
+                    include.into_iter().map(|p| UrlPattern::regex(p).ok().unwrap()).collect(),
+                    exclude.into_iter().map(|p| UrlPattern::glob(p).ok().unwrap()).collect(),
+                );
+                assert_eq!(scope.matches(&Url::parse(url).ok().unwrap()), exp);
+            }
+        )*
+        }
+    }
+
+    scope_tests! {
+        scope_0: (vec![], vec![], "https://example.com/anything", true),
+        scope_1: (vec!["^/docs/"], vec![], "https://example.com/docs/a", true),
+        scope_2: (vec!["^/docs/"], vec![], "https://example.com/blog/a", false),
+        scope_3: (vec!["^/docs/", "^/api/"], vec![], "https://example.com/api/v1", true),
+        scope_4: (vec!["^/docs/"], vec!["/docs/private/**"], "https://example.com/docs/private/a", false),
+        scope_5: (vec![], vec!["/logout"], "https://example.com/logout", false),
+        scope_6: (vec![], vec!["/logout"], "https://example.com/logout/now", true),
+        scope_7: (vec!["action=edit"], vec![], "https://example.com/w?title=a&action=edit", true),
+    }
+
+    #[test]
+    fn invalid_regex() {
+        assert!(UrlPattern::regex("(unclosed").is_err());
+    }
+}
@@ -73,6 +73,14 @@ impl SitePolicy {
             }
         }
     }
+
+    /// Returns if the given url matches the site visiting policy of any of
+    /// the given (seed) urls.
+    pub fn matches_any(&self, source_urls: &[Url], target_url: &Url) -> bool {
+        source_urls
+            .iter()
+            .any(|s| self.matches_policy(s, target_url))
+    }
 }
 
 #[cfg(test)]
@@ -166,4 +174,29 @@ mod tests {
         all_14: (SitePolicy::All, "https://foo.bar.example.com", "https://abc.example.co.uk", true),
         all_15: (SitePolicy::All, "https://foo.bar.example.com/a/b?c=d&e=f", "https://abc.example.co.uk/a/b/c?d=e#bar", true),
     }
+
+    macro_rules! site_policy_any_tests {
+        ($($name:ident: $value:expr,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let (policy, srcs, tgt, result): (SitePolicy, &[&str], &str, bool) = $value;
+                let srcs: Vec<Url> = srcs.iter().map(|s| Url::parse(s).ok().unwrap()).collect();
+                assert_eq!(
+                    policy.matches_any(&srcs, &Url::parse(tgt).ok().unwrap()),
+                    result
+                );
+            }
+        )*
+        }
+    }
+
+    site_policy_any_tests! {
+        any_0: (SitePolicy::Same, &["https://example.com", "https://example.org"], "https://example.org/a", true),
+        any_1: (SitePolicy::Same, &["https://example.com", "https://example.org"], "https://example.com/a", true),
+        any_2: (SitePolicy::Same, &["https://example.com", "https://example.org"], "https://example.net/a", false),
+        any_3: (SitePolicy::Subdomain, &["https://example.com", "https://example.org"], "https://www.example.org", true),
+        any_4: (SitePolicy::Sibling, &["https://a.example.com", "file:///tmp/"], "https://b.example.com", true),
+        any_5: (SitePolicy::Same, &[], "https://example.com", false),
+    }
 }
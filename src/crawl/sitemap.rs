@@ -0,0 +1,186 @@
+use flate2::read::GzDecoder;
+use reqwest::Url;
+use roxmltree::ParsingOptions;
+use std::io::Read;
+
+use crate::error::Error;
+
+/// Upper limit on the uncompressed size of a sitemap (per the sitemaps protocol).
+const MAX_SITEMAP_SIZE: u64 = 50 * 1024 * 1024;
+/// Magic bytes that start a gzip stream.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Urls parsed from a sitemap document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sitemap {
+    /// A sitemap index, listing the urls of other sitemaps.
+    Index(Vec<String>),
+    /// A set of page urls.
+    UrlSet(Vec<String>),
+}
+
+impl Sitemap {
+    /// Parses a sitemap document, which may be gzipped, and may be either an
+    /// xml sitemap/sitemap index or a plain text list of urls.
+    pub fn parse(buf: &[u8]) -> Result<Self, Error> {
+        let mut content = String::new();
+        if buf.starts_with(&GZIP_MAGIC) {
+            GzDecoder::new(buf)
+                .take(MAX_SITEMAP_SIZE)
+                .read_to_string(&mut content)?;
+        } else {
+            content = String::from_utf8_lossy(buf).to_string();
+        }
+
+        let trimmed = content.trim_start_matches('\u{feff}').trim_start();
+        if trimmed.starts_with('<') {
+            Self::parse_xml(trimmed)
+        } else {
+            Ok(Self::parse_text(trimmed))
+        }
+    }
+
+    /// Parses an xml `<urlset>` or `<sitemapindex>` document.
+    fn parse_xml(content: &str) -> Result<Self, Error> {
+        let opts = ParsingOptions {
+            allow_dtd: true,
+            ..Default::default()
+        };
+        let doc = roxmltree::Document::parse_with_options(content, opts)?;
+        let root = doc.root_element();
+        let (is_index, parent) = match root.tag_name().name() {
+            "sitemapindex" => (true, "sitemap"),
+            "urlset" => (false, "url"),
+            other => {
+                return Err(Error::GeneralError(format!(
+                    "unexpected sitemap root element: {}",
+                    other
+                )))
+            }
+        };
+
+        // only direct `<loc>` children of entries; skips extensions such as `<image:loc>`
+        let locs = root
+            .children()
+            .filter(|n| n.tag_name().name() == parent)
+            .flat_map(|n| n.children().filter(|c| c.tag_name().name() == "loc"))
+            .filter_map(|n| n.text())
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .collect();
+
+        if is_index {
+            Ok(Self::Index(locs))
+        } else {
+            Ok(Self::UrlSet(locs))
+        }
+    }
+
+    /// Parses a plain text sitemap, one url per line.
+    fn parse_text(content: &str) -> Self {
+        let urls = content
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| Url::parse(l).is_ok())
+            .map(|l| l.to_string())
+            .collect();
+        Self::UrlSet(urls)
+    }
+}
+
+/// Returns the default sitemap url for the host of the given url.
+pub fn sitemap_url(url: &Url) -> Option<Url> {
+    url.join("/sitemap.xml").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    const URLSET: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9"
+        xmlns:image="http://www.google.com/schemas/sitemap-image/1.1">
+  <url>
+    <loc>https://example.com/</loc>
+    <lastmod>2024-01-01</lastmod>
+  </url>
+  <url>
+    <loc> https://example.com/a/b </loc>
+    <image:image><image:loc>https://example.com/a.png</image:loc></image:image>
+  </url>
+</urlset>"#;
+
+    const INDEX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <sitemap><loc>https://example.com/sitemap-1.xml</loc></sitemap>
+  <sitemap><loc>https://example.com/sitemap-2.xml.gz</loc></sitemap>
+</sitemapindex>"#;
+
+    #[test]
+    fn urlset() {
+        assert_eq!(
+            Sitemap::parse(URLSET.as_bytes()).ok().unwrap(),
+            Sitemap::UrlSet(vec![
+                "https://example.com/".to_string(),
+                "https://example.com/a/b".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn index() {
+        assert_eq!(
+            Sitemap::parse(INDEX.as_bytes()).ok().unwrap(),
+            Sitemap::Index(vec![
+                "https://example.com/sitemap-1.xml".to_string(),
+                "https://example.com/sitemap-2.xml.gz".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn gzipped() {
+        let mut enc = GzEncoder::new(Vec::new(), Compression::default());
+        enc.write_all(INDEX.as_bytes()).unwrap();
+        let buf = enc.finish().unwrap();
+        assert_eq!(
+            Sitemap::parse(&buf).ok().unwrap(),
+            Sitemap::parse(INDEX.as_bytes()).ok().unwrap()
+        );
+    }
+
+    #[test]
+    fn text() {
+        assert_eq!(
+            Sitemap::parse(b"https://example.com/a\nnot a url\n\nhttps://example.com/b\n")
+                .ok()
+                .unwrap(),
+            Sitemap::UrlSet(vec![
+                "https://example.com/a".to_string(),
+                "https://example.com/b".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn doctype() {
+        let doctype = URLSET.replacen(
+            "?>",
+            "?>\n<!DOCTYPE urlset [<!ENTITY site \"https://example.com\">]>",
+            1,
+        );
+        let doctype = doctype.replacen("https://example.com/a/b", "&site;/a/b", 1);
+        assert_eq!(
+            Sitemap::parse(doctype.as_bytes()).ok().unwrap(),
+            Sitemap::parse(URLSET.as_bytes()).ok().unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(Sitemap::parse(b"<html><body>nope</body></html>").is_err());
+    }
+}
@@ -0,0 +1,110 @@
+use reqwest::StatusCode;
+
+/// Classes of HTTP response status codes.
+#[derive(Copy, Debug, Clone, PartialEq)]
+pub enum StatusClass {
+    /// 1xx responses.
+    Informational,
+    /// 2xx responses.
+    Success,
+    /// 3xx responses.
+    Redirect,
+    /// 4xx responses.
+    ClientError,
+    /// 5xx responses.
+    ServerError,
+    /// Anything outside of the standard ranges.
+    Unknown,
+}
+
+/// Display implementation.
+impl std::fmt::Display for StatusClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Informational => write!(f, "Informational"),
+            Self::Success => write!(f, "Success"),
+            Self::Redirect => write!(f, "Redirect"),
+            Self::ClientError => write!(f, "ClientError"),
+            Self::ServerError => write!(f, "ServerError"),
+            Self::Unknown => write!(f, "Unknown"),
+        }
+    }
+}
+
+impl StatusClass {
+    /// Returns the class of the given status code.
+    pub fn from_status(status: StatusCode) -> Self {
+        match status.as_u16() {
+            100..=199 => Self::Informational,
+            200..=299 => Self::Success,
+            300..=399 => Self::Redirect,
+            400..=499 => Self::ClientError,
+            500..=599 => Self::ServerError,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// Inclusive ranges of status codes for which responses are accepted.
+#[derive(Debug, Clone)]
+pub struct StatusRanges(Vec<(u16, u16)>);
+
+impl Default for StatusRanges {
+    /// Returns ranges that accept only successful (2xx) responses.
+    fn default() -> Self {
+        Self(vec![(200, 299)])
+    }
+}
+
+impl StatusRanges {
+    /// Returns a new StatusRanges instance.
+    pub fn new(ranges: Vec<(u16, u16)>) -> Self {
+        Self(ranges)
+    }
+
+    /// Returns whether or not the given status code falls within any range.
+    pub fn contains(&self, status: StatusCode) -> bool {
+        let code = status.as_u16();
+        self.0.iter().any(|(lo, hi)| *lo <= code && code <= *hi)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! status_class_tests {
+        ($($name:ident: $value:expr,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let (code, class, accepted) = $value;
+                let status = StatusCode::from_u16(code).ok().unwrap();
+                assert_eq!(StatusClass::from_status(status), class);
+                assert_eq!(StatusRanges::default().contains(status), accepted);
+            }
+        )*
+        }
+    }
+
+    status_class_tests! {
+        status_0: (101, StatusClass::Informational, false),
+        status_1: (200, StatusClass::Success, true),
+        status_2: (204, StatusClass::Success, true),
+        status_3: (299, StatusClass::Success, true),
+        status_4: (304, StatusClass::Redirect, false),
+        status_5: (404, StatusClass::ClientError, false),
+        status_6: (500, StatusClass::ServerError, false),
+        status_7: (999, StatusClass::Unknown, false),
+    }
+
+    #[test]
+    fn custom_ranges() {
+        let ranges = StatusRanges::new(vec![(200, 200), (400, 404)]);
+        let accepted: Vec<u16> = [200, 201, 399, 400, 403, 404, 405]
+            .into_iter()
+            .filter(|c| ranges.contains(StatusCode::from_u16(*c).ok().unwrap()))
+            .collect();
+        assert_eq!(accepted, vec![200, 400, 403, 404]);
+    }
+}
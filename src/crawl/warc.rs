@@ -0,0 +1,728 @@
+use bytes::Bytes;
+use flate2::read::{DeflateDecoder, MultiGzDecoder};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use reqwest::header::{
+    HeaderMap, HeaderName, HeaderValue, CONTENT_ENCODING, CONTENT_LENGTH, TRANSFER_ENCODING,
+};
+use reqwest::{StatusCode, Url};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::hash::{BuildHasher, Hasher};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::Error;
+use crate::extract::Document;
+
+use super::UrlNormalizer;
+
+/// Version of the WARC format written.
+const WARC_VERSION: &str = "WARC/1.1";
+/// Magic bytes that start a gzip stream.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// Extension field naming the url originally requested, when a response
+/// was served from another url after redirects.
+const REQUESTED_URI: &str = "WDict-Requested-URI";
+/// Field of the `warcinfo` record naming the url crawling initiated from.
+const STARTING_URL: &str = "wdict-starting-url";
+
+/// Writes fetched responses to a WARC (Web ARChive) file, so that documents
+/// may be extracted from again without re-crawling; files ending in `.gz`
+/// are written with each record gzipped, per convention.
+#[derive(Debug)]
+pub struct WarcWriter {
+    out: Arc<Mutex<BufWriter<File>>>,
+    gzip: bool,
+}
+
+impl Clone for WarcWriter {
+    /// Returns a clone/handle of the given WarcWriter.
+    fn clone(&self) -> Self {
+        WarcWriter {
+            out: Arc::clone(&self.out),
+            gzip: self.gzip,
+        }
+    }
+}
+
+impl WarcWriter {
+    /// Returns a new WarcWriter instance writing to the given path, either
+    /// truncating or appending to any existing file; a `warcinfo` record
+    /// naming the starting url is written first.
+    pub fn create(path: &str, append: bool, starting_url: &Url) -> Result<Self, Error> {
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(path)?;
+        let writer = Self {
+            out: Arc::new(Mutex::new(BufWriter::new(file))),
+            gzip: path.ends_with(".gz"),
+        };
+        let info = format!(
+            "software: wdict/{}\r\nformat: WARC File Format 1.1\r\n{}: {}\r\n",
+            env!("CARGO_PKG_VERSION"),
+            STARTING_URL,
+            starting_url
+        );
+        writer.write_record(
+            "warcinfo",
+            None,
+            "application/warc-fields",
+            &[],
+            info.as_bytes(),
+        )?;
+        Ok(writer)
+    }
+
+    /// Returns the underlying file, locked.
+    fn out(&self) -> MutexGuard<'_, BufWriter<File>> {
+        match self.out.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// Write a `response` record of the given document, as served with the
+    /// given status code in response to a request for the given url.
+    pub fn write_response(
+        &self,
+        url: &Url,
+        status: StatusCode,
+        doc: &Document,
+    ) -> Result<(), Error> {
+        let target = doc.url().unwrap_or(url);
+        let mut block = format!(
+            "HTTP/1.1 {} {}\r\n",
+            status.as_u16(),
+            status.canonical_reason().unwrap_or("")
+        )
+        .into_bytes();
+        for (name, value) in doc.headers().iter() {
+            // bodies are held de-chunked
+            if name == TRANSFER_ENCODING {
+                continue;
+            }
+            block.extend_from_slice(name.as_str().as_bytes());
+            block.extend_from_slice(b": ");
+            block.extend_from_slice(value.as_bytes());
+            block.extend_from_slice(b"\r\n");
+        }
+        block.extend_from_slice(b"\r\n");
+        block.extend_from_slice(doc.body());
+
+        let mut fields = Vec::new();
+        if target != url {
+            fields.push((REQUESTED_URI, url.to_string()));
+        }
+        if doc.truncated() {
+            fields.push(("WARC-Truncated", "length".to_string()));
+        }
+        self.write_record(
+            "response",
+            Some(target),
+            "application/http;msgtype=response",
+            &fields,
+            &block,
+        )
+    }
+
+    /// Write a record of the given type and content, flushing it to the file
+    /// so the archive is intact should crawling be interrupted.
+    fn write_record(
+        &self,
+        kind: &str,
+        target: Option<&Url>,
+        content_type: &str,
+        fields: &[(&str, String)],
+        block: &[u8],
+    ) -> Result<(), Error> {
+        let mut record = format!(
+            "{}\r\nWARC-Type: {}\r\nWARC-Record-ID: <urn:uuid:{}>\r\nWARC-Date: {}\r\n",
+            WARC_VERSION,
+            kind,
+            uuid(),
+            warc_date(SystemTime::now())
+        );
+        if let Some(t) = target {
+            record.push_str(&format!("WARC-Target-URI: {}\r\n", t));
+        }
+        for (name, value) in fields {
+            record.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        record.push_str(&format!(
+            "Content-Type: {}\r\nContent-Length: {}\r\n\r\n",
+            content_type,
+            block.len()
+        ));
+        let mut record = record.into_bytes();
+        record.extend_from_slice(block);
+        record.extend_from_slice(b"\r\n\r\n");
+
+        let mut out = self.out();
+        if self.gzip {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&record)?;
+            out.write_all(&encoder.finish()?)?;
+        } else {
+            out.write_all(&record)?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+}
+/// A response read from a WARC file.
+#[derive(Debug, Clone)]
+pub struct ArchivedResponse {
+    /// Status code the response was served with.
+    pub status: StatusCode,
+    /// Headers the response was served with, excluding those describing
+    /// transfer or content encodings, which are decoded.
+    pub headers: HeaderMap,
+    /// Body of the response.
+    pub body: Bytes,
+    /// Url the response was served from.
+    pub url: Url,
+    /// Whether or not the body was cut short of the full document.
+    pub truncated: bool,
+}
+
+/// A record read from a WARC file; its header fields and content block.
+struct Record {
+    fields: Vec<(String, String)>,
+    block: Vec<u8>,
+}
+
+impl Record {
+    /// Returns the value of the named header field, if any.
+    fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Returns the value of the named header field, parsed as a url.
+    fn url_field(&self, name: &str) -> Option<Url> {
+        self.field(name)
+            .map(|u| u.trim_start_matches('<').trim_end_matches('>'))
+            .and_then(|u| Url::parse(u).ok())
+    }
+
+    /// Returns whether or not the record holds an HTTP response.
+    fn is_http_response(&self) -> bool {
+        self.field("WARC-Type") == Some("response")
+            && self
+                .field("Content-Type")
+                .is_some_and(|c| c.starts_with("application/http"))
+    }
+
+    /// Returns the starting url named by a `warcinfo` record, if any.
+    fn starting_url(&self) -> Option<Url> {
+        if self.field("WARC-Type") != Some("warcinfo") {
+            return None;
+        }
+        parse_fields(&self.block)
+            .into_iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(STARTING_URL))
+            .and_then(|(_, v)| Url::parse(&v).ok())
+    }
+}
+
+/// Responses read from a WARC file, by url; served in place of the web to
+/// extract from archived documents.
+#[derive(Debug, Clone)]
+pub struct WarcArchive(Arc<HashMap<String, ArchivedResponse>>);
+
+impl WarcArchive {
+    /// Reads the WARC file at the given path; urls are normalized as they
+    /// would be while crawling.
+    pub fn open(path: &str, normalizer: &UrlNormalizer) -> Result<Self, Error> {
+        Self::read(BufReader::new(File::open(path)?), normalizer)
+    }
+
+    /// Reads the records of a WARC file (which may be gzipped), keeping only
+    /// `response` records of HTTP responses.
+    pub fn read(reader: impl BufRead, normalizer: &UrlNormalizer) -> Result<Self, Error> {
+        let mut reader = decoded(reader)?;
+        let mut responses = HashMap::new();
+        let mut requested = Vec::new();
+        while let Some(record) = read_record(&mut reader)? {
+            if !record.is_http_response() {
+                continue;
+            }
+            let url = match record.url_field("WARC-Target-URI") {
+                Some(u) => u,
+                None => continue,
+            };
+            let mut response = match parse_http_response(&record.block, &url) {
+                Some(r) => r,
+                None => continue,
+            };
+            response.truncated = record.field("WARC-Truncated").is_some();
+            if let Some(r) = record.url_field(REQUESTED_URI) {
+                requested.push((normalizer.normalize(&r).to_string(), response.clone()));
+            }
+            // later responses replace earlier ones, but a 304 only confirms
+            // what was archived before
+            let url = normalizer.normalize(&url).to_string();
+            match responses.get(&url) {
+                Some(_) if response.status == StatusCode::NOT_MODIFIED => {}
+                _ => {
+                    responses.insert(url, response);
+                }
+            }
+        }
+        // responses for the urls they were served from take precedence
+        for (url, response) in requested {
+            responses.entry(url).or_insert(response);
+        }
+        Ok(Self(Arc::new(responses)))
+    }
+
+    /// Returns the number of archived responses, by url.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns whether or not there are no archived responses.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the response archived for the given (normalized) url, if any.
+    pub fn get(&self, url: &Url) -> Option<&ArchivedResponse> {
+        self.0.get(url.as_str())
+    }
+}
+
+/// Returns the url crawling initiated from when the WARC file at the given
+/// path was written, or else the url of its first response; records are
+/// only read as far as needed.
+pub fn warc_starting_url(path: &str) -> Result<Option<Url>, Error> {
+    starting_url(BufReader::new(File::open(path)?))
+}
+
+/// Returns the url named by the first `warcinfo` record naming a starting
+/// url, or by the first response record, whichever comes first.
+fn starting_url(reader: impl BufRead) -> Result<Option<Url>, Error> {
+    let mut reader = decoded(reader)?;
+    while let Some(record) = read_record(&mut reader)? {
+        if let Some(u) = record.starting_url() {
+            return Ok(Some(u));
+        }
+        if record.is_http_response() {
+            if let Some(u) = record.url_field("WARC-Target-URI") {
+                return Ok(Some(u));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Returns a reader of the records of a WARC file, decompressing the file
+/// if it is gzipped.
+fn decoded<'a>(mut reader: impl BufRead + 'a) -> Result<Box<dyn BufRead + 'a>, Error> {
+    if reader.fill_buf()?.starts_with(&GZIP_MAGIC) {
+        return Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader))));
+    }
+    Ok(Box::new(reader))
+}
+
+/// Reads the next record, if any, returning its header fields and content
+/// block.
+fn read_record(reader: &mut dyn BufRead) -> Result<Option<Record>, Error> {
+    let mut line = Vec::new();
+    // records are separated by blank lines
+    while line.trim_ascii().is_empty() {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok(None);
+        }
+    }
+    if !line.starts_with(b"WARC/") {
+        return Err(Error::GeneralError(
+            "invalid warc record; expected a 'WARC/' version line".to_string(),
+        ));
+    }
+    let mut fields = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            return Err(Error::GeneralError(
+                "invalid warc record; unterminated header".to_string(),
+            ));
+        }
+        let line = String::from_utf8_lossy(&line);
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((n, v)) = line.split_once(':') {
+            fields.push((n.trim().to_string(), v.trim().to_string()));
+        }
+    }
+    let len: u64 = fields
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case("Content-Length"))
+        .and_then(|(_, v)| v.parse().ok())
+        .ok_or(Error::GeneralError(
+            "invalid warc record; missing Content-Length".to_string(),
+        ))?;
+    let mut block = Vec::new();
+    reader.take(len).read_to_end(&mut block)?;
+    if (block.len() as u64) < len {
+        return Err(Error::GeneralError(
+            "invalid warc record; truncated content".to_string(),
+        ));
+    }
+    Ok(Some(Record { fields, block }))
+}
+
+/// Returns the position of the blank line ending a header section, along
+/// with the position just past it.
+fn find_header_end(buf: &[u8]) -> Option<(usize, usize)> {
+    let crlf = buf
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .map(|i| (i, i + 4));
+    let lf = buf
+        .windows(2)
+        .position(|w| w == b"\n\n")
+        .map(|i| (i, i + 2));
+    match (crlf, lf) {
+        (Some(c), Some(l)) if l.0 < c.0 => Some(l),
+        (Some(c), _) => Some(c),
+        (None, l) => l,
+    }
+}
+
+/// Parses `Name: value` lines, skipping any that are not.
+fn parse_fields(buf: &[u8]) -> Vec<(String, String)> {
+    String::from_utf8_lossy(buf)
+        .lines()
+        .filter_map(|l| l.split_once(':'))
+        .map(|(n, v)| (n.trim().to_string(), v.trim().to_string()))
+        .collect()
+}
+
+/// Parses an HTTP response message, decoding chunked and compressed bodies.
+fn parse_http_response(block: &[u8], url: &Url) -> Option<ArchivedResponse> {
+    let (end, start) = find_header_end(block).unwrap_or((block.len(), block.len()));
+    let head = String::from_utf8_lossy(&block[..end]);
+    let mut lines = head.lines();
+    let status_line = lines.next()?;
+    if !status_line.starts_with("HTTP/") {
+        return None;
+    }
+    let code = status_line.split_whitespace().nth(1)?;
+    let status = StatusCode::from_bytes(code.as_bytes()).ok()?;
+
+    let mut headers = HeaderMap::new();
+    for line in lines {
+        if let Some((name, value)) = line.split_once(':') {
+            if let (Ok(n), Ok(v)) = (
+                HeaderName::from_bytes(name.trim().as_bytes()),
+                HeaderValue::from_str(value.trim()),
+            ) {
+                headers.append(n, v);
+            }
+        }
+    }
+
+    let mut body = block[start..].to_vec();
+    let chunked = headers
+        .get(TRANSFER_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.to_lowercase().contains("chunked"));
+    if chunked {
+        body = dechunk(&body);
+    }
+    headers.remove(TRANSFER_ENCODING);
+    let encoding = headers
+        .get(CONTENT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.trim().to_lowercase());
+    let decoded = match encoding.as_deref() {
+        Some("gzip" | "x-gzip") => decompress(MultiGzDecoder::new(&body[..])),
+        Some("deflate") => decompress(DeflateDecoder::new(&body[..])),
+        _ => None,
+    };
+    if let Some(d) = decoded {
+        body = d;
+        headers.remove(CONTENT_ENCODING);
+    }
+    headers.remove(CONTENT_LENGTH);
+
+    Some(ArchivedResponse {
+        status,
+        headers,
+        body: Bytes::from(body),
+        url: url.clone(),
+        truncated: false,
+    })
+}
+
+/// Returns all bytes read from the given decoder, if it could be read.
+fn decompress(mut decoder: impl Read) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    decoder.read_to_end(&mut out).ok()?;
+    Some(out)
+}
+
+/// Returns the payload of a body in the chunked transfer coding; malformed
+/// chunks end the payload.
+fn dechunk(buf: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut rest = buf;
+    while let Some(eol) = rest.windows(2).position(|w| w == b"\r\n") {
+        let size = String::from_utf8_lossy(&rest[..eol]);
+        let size = size.split(';').next().unwrap_or("").trim();
+        let len = match usize::from_str_radix(size, 16) {
+            Ok(0) | Err(_) => break,
+            Ok(l) => l,
+        };
+        let start = eol + 2;
+        // sizes are untrusted; a body cut short ends at the record's end
+        let end = start.saturating_add(len).min(rest.len());
+        out.extend_from_slice(&rest[start..end]);
+        rest = rest.get(end + 2..).unwrap_or(&[]);
+    }
+    out
+}
+
+/// Returns a random (version 4) uuid.
+fn uuid() -> String {
+    let mut bytes = [0u8; 16];
+    for (i, half) in bytes.chunks_mut(8).enumerate() {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_usize(i);
+        half.copy_from_slice(&hasher.finish().to_be_bytes());
+    }
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+/// Returns the given time as a WARC date; i.e. `YYYY-MM-DDThh:mm:ssZ`.
+fn warc_date(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (days, rem) = ((secs / 86400) as i64, secs % 86400);
+    // civil from days; see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::CONTENT_TYPE;
+    use std::time::Duration;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).ok().unwrap()
+    }
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("wdict-test-{}-{}", std::process::id(), name))
+            .to_string_lossy()
+            .to_string()
+    }
+
+    fn html_doc(body: &'static str) -> Document {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/html"));
+        headers.insert(TRANSFER_ENCODING, HeaderValue::from_static("chunked"));
+        Document::with_headers(headers, Bytes::from_static(body.as_bytes()))
+    }
+
+    macro_rules! date_tests {
+        ($($name:ident: $value:expr,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let (secs, exp): (u64, &str) = $value;
+                assert_eq!(warc_date(UNIX_EPOCH + Duration::from_secs(secs)), exp);
+            }
+        )*
+        }
+    }
+
+    date_tests! {
+        date_0: (0, "1970-01-01T00:00:00Z"),
+        date_1: (951782400, "2000-02-29T00:00:00Z"),
+        date_2: (1791847283, "2026-10-12T23:21:23Z"),
+        date_3: (4102444799, "2099-12-31T23:59:59Z"),
+    }
+
+    #[test]
+    fn round_trip() {
+        for name in ["round-trip.warc", "round-trip.warc.gz"] {
+            let path = temp_path(name);
+            let start = url("https://example.com/");
+            let writer = WarcWriter::create(&path, false, &start).ok().unwrap();
+            let mut doc = html_doc("<p>hello archive</p>");
+            doc.set_url(url("https://example.com/home"));
+            assert!(writer.write_response(&start, StatusCode::OK, &doc).is_ok());
+            let mut doc = html_doc("<p>oth");
+            doc.set_truncated(true);
+            assert!(writer
+                .write_response(
+                    &url("https://example.com/other"),
+                    StatusCode::NOT_FOUND,
+                    &doc
+                )
+                .is_ok());
+
+            let archive = WarcArchive::open(&path, &UrlNormalizer::default())
+                .ok()
+                .unwrap();
+            let starting = warc_starting_url(&path).ok().unwrap();
+            let _ = std::fs::remove_file(&path);
+            assert_eq!(starting, Some(start.clone()));
+            assert_eq!(archive.len(), 3);
+            let home = archive.get(&url("https://example.com/home")).unwrap();
+            assert_eq!(home.status, StatusCode::OK);
+            assert_eq!(&home.body[..], b"<p>hello archive</p>");
+            assert_eq!(home.headers.get(CONTENT_TYPE).unwrap(), "text/html");
+            assert!(home.headers.get(TRANSFER_ENCODING).is_none());
+            // served after redirects
+            let requested = archive.get(&start).unwrap();
+            assert_eq!(requested.url.as_str(), "https://example.com/home");
+            let other = archive.get(&url("https://example.com/other")).unwrap();
+            assert_eq!(other.status, StatusCode::NOT_FOUND);
+            assert!(other.truncated);
+            assert!(!home.truncated);
+        }
+    }
+
+    #[test]
+    fn later_responses() {
+        let path = temp_path("later.warc");
+        let page = url("https://example.com/page");
+        let writer = WarcWriter::create(&path, false, &page).ok().unwrap();
+        let responses = [
+            (StatusCode::SERVICE_UNAVAILABLE, "<p>busy</p>"),
+            (StatusCode::OK, "<p>fresh</p>"),
+            (StatusCode::NOT_MODIFIED, ""),
+        ];
+        for (status, body) in responses {
+            assert!(writer
+                .write_response(&page, status, &html_doc(body))
+                .is_ok());
+        }
+
+        let archive = WarcArchive::open(&path, &UrlNormalizer::default())
+            .ok()
+            .unwrap();
+        let _ = std::fs::remove_file(&path);
+        let archived = archive.get(&page).unwrap();
+        assert_eq!(archived.status, StatusCode::OK);
+        assert_eq!(&archived.body[..], b"<p>fresh</p>");
+    }
+
+    #[test]
+    fn foreign_records() {
+        let http = b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n7;ext=1\r\n, world\r\n0\r\n\r\n";
+        let mut warc = Vec::new();
+        warc.extend_from_slice(b"WARC/1.0\r\nWARC-Type: request\r\nWARC-Target-URI: <http://example.org/a>\r\nContent-Length: 3\r\n\r\nGET\r\n\r\n");
+        warc.extend_from_slice(
+            format!(
+                "WARC/1.0\r\nWARC-Type: response\r\nWARC-Target-URI: <http://example.org/a>\r\nContent-Type: application/http; msgtype=response\r\nContent-Length: {}\r\n\r\n",
+                http.len()
+            )
+            .as_bytes(),
+        );
+        warc.extend_from_slice(http);
+        warc.extend_from_slice(b"\r\n\r\n");
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"compressed").ok().unwrap();
+        let mut http = b"HTTP/1.0 200 OK\nContent-Encoding: gzip\nContent-Length: 30\n\n".to_vec();
+        http.extend_from_slice(&encoder.finish().ok().unwrap());
+        warc.extend_from_slice(
+            format!(
+                "WARC/1.0\nWARC-Type: response\nWARC-Target-URI: http://example.org/b\nContent-Type: application/http\nContent-Length: {}\n\n",
+                http.len()
+            )
+            .as_bytes(),
+        );
+        warc.extend_from_slice(&http);
+
+        let archive = WarcArchive::read(&warc[..], &UrlNormalizer::default())
+            .ok()
+            .unwrap();
+        assert_eq!(archive.len(), 2);
+        let a = archive.get(&url("http://example.org/a")).unwrap();
+        assert_eq!(&a.body[..], b"hello, world");
+        assert!(!a.truncated);
+        let b = archive.get(&url("http://example.org/b")).unwrap();
+        assert_eq!(&b.body[..], b"compressed");
+        assert!(b.headers.get(CONTENT_ENCODING).is_none());
+        let starting = starting_url(&warc[..]).ok().unwrap();
+        assert_eq!(starting, Some(url("http://example.org/a")));
+
+        assert!(WarcArchive::read(&b"not a warc"[..], &UrlNormalizer::default()).is_err());
+        let truncated = &warc[..warc.len() - 20];
+        assert!(WarcArchive::read(truncated, &UrlNormalizer::default()).is_err());
+    }
+
+    #[test]
+    fn oversized_chunk() {
+        assert_eq!(dechunk(b"ffffffffffffffff\r\nshort"), b"short");
+        assert_eq!(dechunk(b"3\r\nabc\r\n10\r\nde"), b"abcde");
+
+        let http = b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nTransfer-Encoding: chunked\r\n\r\nffffffffffffffff\r\nbody\r\n0\r\n\r\n";
+        let mut warc = format!(
+            "WARC/1.0\r\nWARC-Type: response\r\nWARC-Target-URI: http://example.org/c\r\nContent-Type: application/http; msgtype=response\r\nContent-Length: {}\r\n\r\n",
+            http.len()
+        )
+        .into_bytes();
+        warc.extend_from_slice(http);
+        warc.extend_from_slice(b"\r\n\r\n");
+        let archive = WarcArchive::read(&warc[..], &UrlNormalizer::default())
+            .ok()
+            .unwrap();
+        let c = archive.get(&url("http://example.org/c")).unwrap();
+        assert!(c.body.starts_with(b"body"));
+    }
+
+    #[test]
+    fn uuids() {
+        let (a, b) = (uuid(), uuid());
+        assert_ne!(a, b);
+        assert_eq!(a.len(), 36);
+        assert_eq!(&a[14..15], "4");
+    }
+}
@@ -8,6 +8,16 @@ pub enum Error {
     SerdeError(serde_json::Error),
     HeaderNameError(reqwest::header::InvalidHeaderName),
     HeaderValueError(reqwest::header::InvalidHeaderValue),
+    XmlError(roxmltree::Error),
+    RegexError(regex::Error),
+    Pkcs12Error(p12_keystore::error::Error),
+    RetryableError(reqwest::StatusCode, Option<std::time::Duration>),
+    StatusError(reqwest::StatusCode),
+    RedirectError(reqwest::StatusCode, reqwest::Url),
+    NotModifiedError,
+    NotArchivedError(reqwest::Url),
+    OversizedError(u64),
+    LoginError(String),
     EarlyTerminationError,
     GeneralError(String),
 }
@@ -55,6 +65,24 @@ impl From<reqwest::header::InvalidHeaderName> for Error {
     }
 }
 
+impl From<roxmltree::Error> for Error {
+    fn from(e: roxmltree::Error) -> Error {
+        Error::XmlError(e)
+    }
+}
+
+impl From<regex::Error> for Error {
+    fn from(e: regex::Error) -> Error {
+        Error::RegexError(e)
+    }
+}
+
+impl From<p12_keystore::error::Error> for Error {
+    fn from(e: p12_keystore::error::Error) -> Error {
+        Error::Pkcs12Error(e)
+    }
+}
+
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -67,6 +95,18 @@ impl std::fmt::Display for Error {
             }
             Error::HeaderNameError(e) => write!(f, "header error: {}", e),
             Error::HeaderValueError(e) => write!(f, "header error: {}", e),
+            Error::XmlError(e) => write!(f, "xml error: {}", e),
+            Error::RegexError(e) => write!(f, "regex error: {}", e),
+            Error::Pkcs12Error(e) => write!(f, "pkcs12 error: {}", e),
+            Error::RetryableError(s, _) => write!(f, "retryable status code: {}", s),
+            Error::StatusError(s) => write!(f, "unaccepted status code: {}", s),
+            Error::NotModifiedError => write!(f, "not modified"),
+            Error::NotArchivedError(u) => write!(f, "{} not found in archive", u),
+            Error::RedirectError(s, u) => write!(f, "{} redirect to {} not followed", s, u),
+            Error::OversizedError(max) => {
+                write!(f, "document exceeds maximum size of {} bytes", max)
+            }
+            Error::LoginError(s) => write!(f, "login failed: {}", s),
             Error::EarlyTerminationError => write!(f, "terminated early"),
             Error::GeneralError(s) => {
                 write!(f, "parse error: {}", s)
@@ -0,0 +1,358 @@
+use super::FEED_MIME_TYPES;
+use bytes::Bytes;
+use encoding_rs::{Encoding, UTF_8};
+use infer::MatcherType;
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
+use reqwest::Url;
+use std::sync::OnceLock;
+
+/// Number of leading bytes of an html document scanned for a `<meta charset>`.
+const META_PRESCAN_LEN: usize = 1024;
+
+/// Broad classes of documents, determining how they are processed.
+#[derive(Copy, Debug, Clone, PartialEq)]
+pub enum DocKind {
+    /// Html (or xhtml) markup.
+    Html,
+    /// Plain text, or text-based formats without markup of interest.
+    Text,
+    /// RSS or Atom feeds.
+    Feed,
+    /// Binary content, not suitable for word extraction.
+    Binary,
+}
+
+/// Display implementation.
+impl std::fmt::Display for DocKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Html => write!(f, "Html"),
+            Self::Text => write!(f, "Text"),
+            Self::Feed => write!(f, "Feed"),
+            Self::Binary => write!(f, "Binary"),
+        }
+    }
+}
+
+/// A fetched document; the body along with the headers it was served with.
+#[derive(Debug, Clone)]
+pub struct Document {
+    headers: HeaderMap,
+    body: Bytes,
+    /// Whether or not the body was cut short of the full document.
+    truncated: bool,
+    /// Url the document was finally served from, after any redirects.
+    url: Option<Url>,
+    /// Kind of the document, determined on first use.
+    kind: OnceLock<DocKind>,
+    /// Body of the document decoded as text, decoded on first use.
+    text: OnceLock<String>,
+}
+
+impl Document {
+    /// Returns a new Document instance, without any headers; the type of the
+    /// document is sniffed from its contents.
+    pub fn new(body: Bytes) -> Self {
+        Self::with_headers(HeaderMap::new(), body)
+    }
+
+    /// Returns a new Document instance, served with the given headers.
+    pub fn with_headers(headers: HeaderMap, body: Bytes) -> Self {
+        Self {
+            headers,
+            body,
+            truncated: false,
+            url: None,
+            kind: OnceLock::new(),
+            text: OnceLock::new(),
+        }
+    }
+
+    /// Returns the headers the document was served with.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Returns the raw body of the document.
+    pub fn body(&self) -> &Bytes {
+        &self.body
+    }
+
+    /// Returns whether or not the body was cut short of the full document.
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Set whether or not the body was cut short of the full document.
+    pub fn set_truncated(&mut self, truncated: bool) {
+        self.truncated = truncated;
+    }
+
+    /// Returns the url the document was finally served from, if known.
+    pub fn url(&self) -> Option<&Url> {
+        self.url.as_ref()
+    }
+
+    /// Set the url the document was finally served from, after any redirects.
+    pub fn set_url(&mut self, url: Url) {
+        self.url = Some(url);
+    }
+
+    /// Returns the declared mime type and charset of the document, if any.
+    pub fn content_type(&self) -> (Option<String>, Option<String>) {
+        match self.headers.get(CONTENT_TYPE).and_then(|v| v.to_str().ok()) {
+            Some(ct) => parse_content_type(ct),
+            None => (None, None),
+        }
+    }
+
+    /// Returns the kind of the document, preferring the declared mime type and
+    /// falling back to sniffing the contents; feeds are often served as
+    /// generic xml, so those are sniffed as well.
+    pub fn kind(&self) -> DocKind {
+        *self.kind.get_or_init(|| self.detect_kind())
+    }
+
+    fn detect_kind(&self) -> DocKind {
+        let mime = self.content_type().0;
+        let kind = match mime.as_deref() {
+            // generic; tells us nothing, so sniff instead
+            None | Some("application/octet-stream") => sniff_kind(&self.body),
+            Some(mime) => kind_from_mime(mime),
+        };
+        let generic = matches!(
+            mime.as_deref(),
+            None | Some(
+                "application/octet-stream" | "application/xml" | "text/xml" | "application/rdf+xml"
+            )
+        );
+        if kind == DocKind::Text && generic && is_feed_root(&self.body) {
+            return DocKind::Feed;
+        }
+        kind
+    }
+
+    /// Returns the document decoded as text; the encoding is taken from a
+    /// byte order mark, the declared charset, or a `<meta charset>` in html
+    /// documents, in that order, defaulting to utf-8.
+    pub fn text(&self) -> &str {
+        self.text.get_or_init(|| self.decode())
+    }
+
+    fn decode(&self) -> String {
+        let declared = self
+            .content_type()
+            .1
+            .and_then(|c| Encoding::for_label(c.as_bytes()));
+        let encoding = match declared {
+            Some(e) => e,
+            None if self.kind() == DocKind::Html => meta_charset(&self.body)
+                .and_then(|c| Encoding::for_label(c.as_bytes()))
+                .unwrap_or(UTF_8),
+            None => UTF_8,
+        };
+        // a byte order mark, if present, overrides the given encoding
+        let (text, _, _) = encoding.decode(&self.body);
+        text.into_owned()
+    }
+}
+
+/// Parses the value of a `Content-Type` header into a lowercase mime type and
+/// charset, if present.
+pub fn parse_content_type(value: &str) -> (Option<String>, Option<String>) {
+    let mut parts = value.split(';');
+    let mime = parts
+        .next()
+        .map(|m| m.trim().to_lowercase())
+        .filter(|m| m.contains('/'));
+    let charset = parts
+        .filter_map(|p| p.split_once('='))
+        .find(|(k, _)| k.trim().eq_ignore_ascii_case("charset"))
+        .map(|(_, v)| {
+            v.trim()
+                .trim_matches(|c| c == '"' || c == '\'')
+                .to_lowercase()
+        })
+        .filter(|c| !c.is_empty());
+    (mime, charset)
+}
+
+/// Returns the kind of document described by the given mime type.
+fn kind_from_mime(mime: &str) -> DocKind {
+    match mime {
+        "text/html" | "application/xhtml+xml" => DocKind::Html,
+        m if FEED_MIME_TYPES.contains(&m) => DocKind::Feed,
+        "application/json"
+        | "application/javascript"
+        | "application/ecmascript"
+        | "application/xml" => DocKind::Text,
+        m if m.starts_with("text/") || m.ends_with("+xml") || m.ends_with("+json") => DocKind::Text,
+        _ => DocKind::Binary,
+    }
+}
+
+/// Returns the kind of document, sniffed from its contents; unrecognized
+/// contents are assumed to be plain text.
+fn sniff_kind(buf: &[u8]) -> DocKind {
+    match infer::get(buf) {
+        Some(kind) if kind.mime_type() == "text/html" => DocKind::Html,
+        Some(kind) if kind.matcher_type() == MatcherType::Text => DocKind::Text,
+        Some(_) => DocKind::Binary,
+        None => DocKind::Text,
+    }
+}
+
+/// Returns whether or not the root element near the start of an xml document
+/// is that of an RSS or Atom feed.
+fn is_feed_root(buf: &[u8]) -> bool {
+    let head = &buf[..buf.len().min(META_PRESCAN_LEN)];
+    let head = String::from_utf8_lossy(head).to_lowercase();
+    // skip the xml declaration, comments, and doctypes
+    let root = head
+        .split('<')
+        .skip(1)
+        .find(|t| !t.starts_with('?') && !t.starts_with('!'));
+    let name: String = root
+        .unwrap_or("")
+        .chars()
+        .take_while(|c| c.is_alphanumeric() || *c == ':')
+        .collect();
+    matches!(name.as_str(), "rss" | "feed" | "rdf:rdf")
+}
+
+/// Returns the charset declared by a `<meta charset>` or
+/// `<meta http-equiv="Content-Type">` element near the start of an html document.
+fn meta_charset(buf: &[u8]) -> Option<String> {
+    let head = &buf[..buf.len().min(META_PRESCAN_LEN)];
+    let head = String::from_utf8_lossy(head).to_lowercase();
+    for tag in head.split("<meta").skip(1) {
+        let tag = tag.split('>').next().unwrap_or("");
+        if let Some((_, rest)) = tag.split_once("charset=") {
+            let charset: String = rest
+                .trim_start_matches(['"', '\''])
+                .chars()
+                .take_while(|c| !matches!(c, '"' | '\'' | ';' | '/' | '>') && !c.is_whitespace())
+                .collect();
+            if !charset.is_empty() {
+                return Some(charset);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn doc(content_type: Option<&str>, body: &[u8]) -> Document {
+        let mut headers = HeaderMap::new();
+        if let Some(ct) = content_type {
+            headers.insert(CONTENT_TYPE, HeaderValue::from_str(ct).unwrap());
+        }
+        Document::with_headers(headers, Bytes::copy_from_slice(body))
+    }
+
+    macro_rules! content_type_tests {
+        ($($name:ident: $value:expr,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let (prov, mime, charset): (&str, Option<&str>, Option<&str>) = $value;
+                assert_eq!(
+                    parse_content_type(prov),
+                    (mime.map(String::from), charset.map(String::from))
+                );
+            }
+        )*
+        }
+    }
+
+    content_type_tests! {
+        content_type_0: ("text/html", Some("text/html"), None),
+        content_type_1: ("text/html; charset=UTF-8", Some("text/html"), Some("utf-8")),
+        content_type_2: ("Text/HTML;Charset=\"Shift_JIS\"", Some("text/html"), Some("shift_jis")),
+        content_type_3: ("text/plain; format=flowed; charset=iso-8859-1", Some("text/plain"), Some("iso-8859-1")),
+        content_type_4: ("text/plain; charset=", Some("text/plain"), None),
+        content_type_5: ("garbage", None, None),
+    }
+
+    macro_rules! kind_tests {
+        ($($name:ident: $value:expr,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let (content_type, body, kind): (Option<&str>, &[u8], DocKind) = $value;
+                assert_eq!(doc(content_type, body).kind(), kind);
+            }
+        )*
+        }
+    }
+
+    kind_tests! {
+        kind_0: (Some("text/html; charset=utf-8"), b"hello", DocKind::Html),
+        kind_1: (Some("application/xhtml+xml"), b"hello", DocKind::Html),
+        kind_2: (Some("text/plain"), b"<html><body>", DocKind::Text),
+        kind_3: (Some("application/json"), b"{}", DocKind::Text),
+        kind_4: (Some("application/rss+xml"), b"<rss>", DocKind::Feed),
+        kind_5: (Some("image/png"), b"hello", DocKind::Binary),
+        kind_6: (Some("application/pdf"), b"hello", DocKind::Binary),
+        kind_7: (Some("application/octet-stream"), b"<html><body>", DocKind::Html),
+        kind_8: (None, b"<html><body>", DocKind::Html),
+        kind_9: (None, b"just some words", DocKind::Text),
+        kind_10: (None, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR", DocKind::Binary),
+        kind_11: (Some("application/atom+xml; charset=utf-8"), b"", DocKind::Feed),
+        kind_12: (Some("text/xml"), b"<?xml version=\"1.0\"?>\n<!-- c --><rss version=\"2.0\">", DocKind::Feed),
+        kind_13: (Some("application/xml"), b"<feed xmlns=\"http://www.w3.org/2005/Atom\">", DocKind::Feed),
+        kind_14: (None, b"<?xml version=\"1.0\"?><rdf:RDF>", DocKind::Feed),
+        kind_15: (Some("application/xml"), b"<urlset>", DocKind::Text),
+        kind_16: (Some("text/plain"), b"<rss>", DocKind::Text),
+        kind_17: (Some("application/xml"), b"<feedback>", DocKind::Text),
+    }
+
+    #[test]
+    fn decode_declared_charset() {
+        let (body, _, _) = encoding_rs::SHIFT_JIS.encode("こんにちは 世界");
+        let d = doc(Some("text/plain; charset=Shift_JIS"), &body);
+        assert_eq!(d.text(), "こんにちは 世界");
+    }
+
+    #[test]
+    fn decode_meta_charset() {
+        let (text, _, _) = encoding_rs::WINDOWS_1251.encode("Привет мир");
+        let mut body = b"<html><head><meta charset=\"windows-1251\"></head><body>".to_vec();
+        body.extend_from_slice(&text);
+        let d = doc(Some("text/html"), &body);
+        assert!(d.text().contains("Привет мир"));
+    }
+
+    #[test]
+    fn decode_meta_http_equiv() {
+        let mut body = b"<html><head><META http-equiv=\"Content-Type\" content=\"text/html; charset=ISO-8859-2\">".to_vec();
+        body.extend_from_slice(b"<body>\xb1\xbf");
+        let d = doc(None, &body);
+        assert!(d.text().ends_with("ąż"));
+    }
+
+    #[test]
+    fn decode_header_over_meta() {
+        let mut body = b"<html><head><meta charset=\"windows-1251\"></head><body>".to_vec();
+        body.extend_from_slice("żółw".as_bytes());
+        let d = doc(Some("text/html; charset=utf-8"), &body);
+        assert!(d.text().ends_with("żółw"));
+    }
+
+    #[test]
+    fn decode_default_utf8() {
+        let d = doc(None, "żółw".as_bytes());
+        assert_eq!(d.text(), "żółw");
+    }
+
+    #[test]
+    fn decode_once() {
+        let d = doc(Some("text/html"), b"<html><body>hello</body></html>");
+        assert!(std::ptr::eq(d.text(), d.text()));
+        assert_eq!(d.clone().text(), d.text());
+    }
+}
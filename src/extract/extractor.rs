@@ -1,12 +1,11 @@
 use bytes::{Bytes, BytesMut};
-use infer;
 use log::debug;
 use scraper::{node::Node, Html};
 use unicode_segmentation::UnicodeSegmentation;
 
 use crate::collections::WordDb;
 
-use super::FilterMode;
+use super::{DocKind, Document, Feed, FilterMode};
 
 /// Extracts words from html documents.
 #[derive(Debug, Clone)]
@@ -21,29 +20,33 @@ impl Extractor {
         Self { opts, words }
     }
 
-    pub fn words_from_doc(&mut self, buf: &Bytes) -> () {
-        let res = infer::get(buf);
-        match res {
-            Some(kind) => match kind.mime_type() {
-                "text/html" => self.words_from_html(&buf),
-                _ => debug!("unsupported mime type: {}", kind),
+    /// Extract words from the provided document, according to its kind;
+    /// binary documents are skipped.
+    pub fn words_from_doc(&mut self, doc: &Document) {
+        match doc.kind() {
+            DocKind::Html => self.words_from_html(doc.text()),
+            DocKind::Text => self.words_from_text(doc.text()),
+            DocKind::Feed => match Feed::parse(doc.text()) {
+                Ok(feed) => self.words_from_text(&feed.text()),
+                Err(e) => {
+                    debug!("unable to parse feed, treating as text: {}", e);
+                    self.words_from_text(doc.text())
+                }
             },
-            None => {
-                //warn!("failure infering mime type");
-                // attempt as plain text file
-                self.words_from_text(&buf);
-            }
+            DocKind::Binary => debug!(
+                "skipping binary document of type: {}",
+                doc.content_type().0.unwrap_or("unknown".to_string())
+            ),
         }
     }
 
-    /// Extract words from the provided document, treating bytes buffer as html.
-    fn words_from_html(&mut self, buf: &Bytes) -> () {
-        let s = String::from_utf8_lossy(&buf).to_string();
+    /// Extract words from the provided document, treating decoded text as html.
+    fn words_from_html(&mut self, s: &str) {
         //let mut fin: String = String::new();
         let mut fin = BytesMut::new();
         {
             // scope protects the html object from async
-            let doc = Html::parse_document(&s);
+            let doc = Html::parse_document(s);
             // note: alternatives to getting all text nodes (regardless if script/styel/etc. or not)
             //for text in document.clone().root_element().text() { ...do something... }
             for d in doc.root_element().descendants() {
@@ -77,10 +80,9 @@ impl Extractor {
         self.filter_text(&fin.freeze());
     }
 
-    /// Extract words from the provided document, treating bytes buffer as plain text.
-    fn words_from_text(&mut self, buf: &Bytes) -> () {
-        //let s = String::from_utf8_lossy(&buf).to_string();
-        self.filter_text(&buf);
+    /// Extract words from the provided document, treating decoded text as plain text.
+    fn words_from_text(&mut self, s: &str) {
+        self.filter_text(&Bytes::copy_from_slice(s.as_bytes()));
     }
 
     /// Filter text based on configured filters and capture resulting words.
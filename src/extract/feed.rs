@@ -0,0 +1,282 @@
+use roxmltree::{Node, ParsingOptions};
+use scraper::Html;
+
+use crate::error::Error;
+
+/// Mime types that feeds are declared with.
+pub const FEED_MIME_TYPES: [&str; 2] = ["application/rss+xml", "application/atom+xml"];
+
+/// Links and text parsed from an RSS or Atom feed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Feed {
+    /// Links to the feed's items.
+    links: Vec<String>,
+    /// Titles and descriptions of the feed and its items, stripped of markup.
+    text: Vec<String>,
+}
+
+impl Feed {
+    /// Parses an RSS (0.9x, 1.0, or 2.0) or Atom feed.
+    pub fn parse(content: &str) -> Result<Self, Error> {
+        let content = content.trim_start_matches('\u{feff}').trim_start();
+        // RSS 0.91 feeds commonly declare the netscape DOCTYPE
+        let opts = ParsingOptions {
+            allow_dtd: true,
+            ..Default::default()
+        };
+        let doc = roxmltree::Document::parse_with_options(content, opts)?;
+        let root = doc.root_element();
+        match root.tag_name().name() {
+            "rss" | "RDF" => Ok(Self::parse_rss(root)),
+            "feed" => Ok(Self::parse_atom(root)),
+            other => Err(Error::GeneralError(format!(
+                "unexpected feed root element: {}",
+                other
+            ))),
+        }
+    }
+
+    /// Parses the items of an RSS feed; in RSS 1.0 (RDF), items are siblings
+    /// of the channel rather than children.
+    fn parse_rss(root: Node) -> Self {
+        let mut feed = Self::empty();
+        for node in root.descendants().filter(|n| n.is_element()) {
+            match node.tag_name().name() {
+                "channel" | "item" => {
+                    for child in node.children().filter(|n| n.is_element()) {
+                        match child.tag_name().name() {
+                            "link" if node.tag_name().name() == "item" => {
+                                feed.push_link(&node_text(child))
+                            }
+                            // `encoded` is `<content:encoded>`
+                            "title" | "description" | "encoded" => {
+                                feed.push_text(&node_text(child))
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        feed
+    }
+
+    /// Parses the entries of an Atom feed.
+    fn parse_atom(root: Node) -> Self {
+        let mut feed = Self::empty();
+        for node in root.children().filter(|n| n.is_element()) {
+            match node.tag_name().name() {
+                "title" | "subtitle" => feed.push_text(&node_text(node)),
+                "entry" => {
+                    for child in node.children().filter(|n| n.is_element()) {
+                        match child.tag_name().name() {
+                            "link" if is_alternate(child) => {
+                                feed.push_link(child.attribute("href").unwrap_or(""))
+                            }
+                            "title" | "summary" | "content" => feed.push_text(&node_text(child)),
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        feed
+    }
+
+    /// Returns an empty Feed.
+    fn empty() -> Self {
+        Self {
+            links: Vec::new(),
+            text: Vec::new(),
+        }
+    }
+
+    /// Save the given link, if not empty.
+    fn push_link(&mut self, link: &str) {
+        let link = link.trim();
+        if !link.is_empty() {
+            self.links.push(link.to_string());
+        }
+    }
+
+    /// Save the given text, stripped of any markup, if not empty.
+    fn push_text(&mut self, text: &str) {
+        let text = strip_markup(text);
+        if !text.trim().is_empty() {
+            self.text.push(text);
+        }
+    }
+
+    /// Returns the links to the feed's items.
+    pub fn links(&self) -> impl Iterator<Item = &String> {
+        self.links.iter()
+    }
+
+    /// Returns the titles and descriptions of the feed and its items.
+    pub fn text(&self) -> String {
+        self.text.join("\n")
+    }
+}
+
+/// Returns whether or not an Atom `<link>` points to the entry itself.
+fn is_alternate(node: Node) -> bool {
+    matches!(node.attribute("rel"), None | Some("alternate"))
+}
+
+/// Returns all text within the given node, including that of any children
+/// (e.g. Atom's `type="xhtml"` content).
+fn node_text(node: Node) -> String {
+    node.descendants()
+        .filter(|n| n.is_text())
+        .filter_map(|n| n.text())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Returns the given text with html markup removed; feed descriptions
+/// commonly hold escaped html.
+fn strip_markup(text: &str) -> String {
+    if !text.contains('<') {
+        return text.to_string();
+    }
+    Html::parse_fragment(text)
+        .root_element()
+        .text()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RSS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:content="http://purl.org/rss/1.0/modules/content/">
+<channel>
+  <title>Example Blog</title>
+  <link>https://example.com/</link>
+  <description>Musings about things</description>
+  <item>
+    <title>First Post</title>
+    <link>https://example.com/posts/first</link>
+    <description>&lt;p&gt;Hello &lt;b&gt;world&lt;/b&gt;&lt;/p&gt;</description>
+  </item>
+  <item>
+    <title>Second Post</title>
+    <link> /posts/second </link>
+    <content:encoded><![CDATA[<p>Encoded <i>content</i></p>]]></content:encoded>
+  </item>
+</channel>
+</rss>"#;
+
+    const RDF: &str = r#"<?xml version="1.0"?>
+<rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#" xmlns="http://purl.org/rss/1.0/">
+  <channel rdf:about="https://example.com/"><title>Old Feed</title><link>https://example.com/</link></channel>
+  <item rdf:about="https://example.com/a"><title>Item A</title><link>https://example.com/a</link></item>
+</rdf:RDF>"#;
+
+    const ATOM: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>Atom Feed</title>
+  <subtitle type="html">&lt;em&gt;News&lt;/em&gt; desk</subtitle>
+  <link href="https://example.com/feed.atom" rel="self"/>
+  <entry>
+    <title>Entry One</title>
+    <link rel="edit" href="https://example.com/edit/1"/>
+    <link href="https://example.com/entries/1"/>
+    <summary>Short summary</summary>
+  </entry>
+  <entry>
+    <title type="text">Entry Two</title>
+    <link rel="alternate" type="text/html" href="https://example.com/entries/2"/>
+    <content type="xhtml"><div xmlns="http://www.w3.org/1999/xhtml"><p>Rich</p><p>content</p></div></content>
+  </entry>
+</feed>"#;
+
+    const RSS_091: &str = r#"<?xml version="1.0" encoding="ISO-8859-1"?>
+<!DOCTYPE rss PUBLIC "-//Netscape Communications//DTD RSS 0.91//EN"
+  "http://my.netscape.com/publish/formats/rss-0.91.dtd">
+<rss version="0.91">
+<channel>
+  <title>Netscape Feed</title>
+  <item>
+    <title>Caf&#233; opens</title>
+    <link>https://example.com/news/1</link>
+  </item>
+</channel>
+</rss>"#;
+
+    #[test]
+    fn rss() {
+        let feed = Feed::parse(RSS).ok().unwrap();
+        assert_eq!(
+            feed.links().collect::<Vec<&String>>(),
+            vec!["https://example.com/posts/first", "/posts/second"]
+        );
+        let text = feed.text();
+        for t in [
+            "Example Blog",
+            "Musings about things",
+            "First Post",
+            "world",
+            "Second Post",
+            "content",
+        ] {
+            assert!(text.contains(t), "missing '{}' in '{}'", t, text);
+        }
+        assert!(!text.contains("<p>"));
+        assert!(!text.contains("https://example.com/"));
+    }
+
+    #[test]
+    fn rdf() {
+        let feed = Feed::parse(RDF).ok().unwrap();
+        assert_eq!(
+            feed.links().collect::<Vec<&String>>(),
+            vec!["https://example.com/a"]
+        );
+        assert_eq!(feed.text(), "Old Feed\nItem A");
+    }
+
+    #[test]
+    fn atom() {
+        let feed = Feed::parse(ATOM).ok().unwrap();
+        assert_eq!(
+            feed.links().collect::<Vec<&String>>(),
+            vec![
+                "https://example.com/entries/1",
+                "https://example.com/entries/2"
+            ]
+        );
+        let text = feed.text();
+        for t in [
+            "Atom Feed",
+            "News",
+            "Entry One",
+            "Short summary",
+            "Entry Two",
+            "Rich",
+        ] {
+            assert!(text.contains(t), "missing '{}' in '{}'", t, text);
+        }
+        assert!(!text.contains("<em>"));
+    }
+
+    #[test]
+    fn rss_doctype() {
+        let feed = Feed::parse(RSS_091).ok().unwrap();
+        assert_eq!(
+            feed.links().collect::<Vec<&String>>(),
+            vec!["https://example.com/news/1"]
+        );
+        assert_eq!(feed.text(), "Netscape Feed\nCaf\u{e9} opens");
+    }
+
+    #[test]
+    fn not_a_feed() {
+        assert!(Feed::parse("<html><body>nope</body></html>").is_err());
+        assert!(Feed::parse("not xml at all").is_err());
+    }
+}
@@ -1,5 +1,9 @@
+mod document;
 mod extractor;
+mod feed;
 mod filter;
 
+pub use self::document::*;
 pub use self::extractor::*;
+pub use self::feed::*;
 pub use self::filter::*;
@@ -3,18 +3,22 @@
 use clap::Parser;
 use indicatif::MultiProgress;
 use indicatif_log_bridge::LogWrapper;
-use log::{error, info, LevelFilter};
+use log::{error, info, warn, LevelFilter};
 use serde_json;
 use std::io::Write;
+use std::time::Duration;
 use std::{fs, process::exit};
 use tokio::signal;
 use tokio::sync::broadcast;
 use tokio::task::JoinHandle;
 
-use wdict::cli::{self, Cli, FilterArg, State};
-use wdict::collections::{UrlDb, WordDb};
-use wdict::crawl::{CrawlMode, CrawlOptions, Crawler};
-use wdict::extract::ExtractOptions;
+use wdict::cli::{self, Cli, LinkArg, State, StdinArg};
+use wdict::collections::{CookieJar, UrlDb, WordDb};
+use wdict::crawl::{
+    CrawlMode, CrawlOptions, Crawler, Har, RetryPolicy, StatusRanges, UrlNormalizer, WarcArchive,
+    WarcWriter,
+};
+use wdict::extract::Extractor;
 use wdict::{Error, Shutdown};
 
 /// Main function.
@@ -25,6 +29,11 @@ async fn main() -> Result<(), Error> {
     let multi = MultiProgress::new();
     setup_logger(multi.clone(), &args.verbose.log_level_filter());
 
+    if args.target.stdin == Some(StdinArg::Document) {
+        extract_stdin(&args);
+        return Ok(());
+    }
+
     let state_res = cli::build_initial_state(&mut args);
     if let Err(e) = state_res {
         error!("{}", e);
@@ -32,8 +41,13 @@ async fn main() -> Result<(), Error> {
     }
     let mut in_state = state_res.unwrap();
     let url = in_state.starting_url.clone();
+    let seeds = in_state.seeds.clone();
 
-    let crawl_mode = if url.scheme() == "file" {
+    let crawl_mode = if args.target.warc_input.is_some() {
+        CrawlMode::Archive
+    } else if args.target.har_input.is_some() {
+        CrawlMode::Har
+    } else if seeds.iter().all(|s| s.scheme() == "file") {
         CrawlMode::Local
     } else {
         CrawlMode::Web
@@ -46,14 +60,55 @@ async fn main() -> Result<(), Error> {
     }
     let headers = headers_res.unwrap();
 
+    let scope_res = cli::build_scope(&args);
+    if let Err(e) = scope_res {
+        error!("{}", e);
+        exit(1);
+    }
+    let scope = scope_res.unwrap();
+
+    let proxy_res = cli::build_proxy(&args);
+    if let Err(e) = proxy_res {
+        error!("{}", e);
+        exit(1);
+    }
+    let proxy = proxy_res.unwrap();
+
+    let auth = cli::build_auth(&args, &seeds);
+
+    let login_res = cli::build_login(&args, &url);
+    if let Err(e) = login_res {
+        error!("{}", e);
+        exit(1);
+    }
+    let login = login_res.unwrap();
+
+    let tls_res = cli::build_tls(&args);
+    if let Err(e) = tls_res {
+        error!("{}", e);
+        exit(1);
+    }
+    let tls = tls_res.unwrap();
+    if tls.insecure() {
+        // shown regardless of verbosity
+        eprintln!(
+            "WARNING: TLS certificate and hostname verification is DISABLED (--insecure); \
+             responses may be intercepted or spoofed"
+        );
+    }
+
     info!(
         "using '{}' as target with crawl mode: {}",
-        &url.as_str(),
+        seeds
+            .iter()
+            .map(|s| s.as_str())
+            .collect::<Vec<&str>>()
+            .join("', '"),
         crawl_mode
     );
 
     let (notify_shutdown, _) = broadcast::channel(1);
-    let copts = CrawlOptions::new(
+    let mut copts = CrawlOptions::new(
         &url,
         args.depth,
         args.include_js,
@@ -65,17 +120,130 @@ async fn main() -> Result<(), Error> {
         args.user_agent.clone(),
         headers,
     );
-    let eopts = ExtractOptions::new(
-        args.min_word_length,
-        args.max_word_length,
-        args.include_js,
-        args.include_css,
-        FilterArg::to_modes(&args.filters),
-    );
+    copts.set_seeds(seeds.clone());
+    copts.set_ignore_robots(args.ignore_robots);
+    copts.set_sitemaps(args.sitemaps);
+    copts.set_retry_policy(RetryPolicy::new(
+        args.max_retries,
+        Duration::from_millis(args.retry_backoff),
+        Duration::from_secs(args.max_retry_delay),
+    ));
+    copts.set_accept_status(StatusRanges::new(args.accept_status.clone()));
+    copts.set_max_doc_size(args.max_doc_size);
+    copts.set_truncate_oversized(args.truncate_oversized);
+    copts.set_refresh(args.refresh);
+    copts.set_connect_timeout(Duration::from_secs(args.connect_timeout));
+    copts.set_timeout(Duration::from_secs(args.timeout));
+    copts.set_max_redirects(args.max_redirects);
+    copts.set_max_pages(args.max_pages);
+    copts.set_max_bytes(args.max_bytes);
+    if args.max_duration > 0 {
+        copts.set_max_duration(Some(Duration::from_secs(args.max_duration)));
+    }
+    copts.set_scope(scope);
+    copts.set_follow_links(LinkArg::to_modes(&args.follow_links));
+    copts.set_follow_rel(args.follow_rel.clone());
+    if !proxy.is_empty() {
+        info!(
+            "routing requests through proxy: {}",
+            [&args.http_proxy, &args.https_proxy, &args.proxy]
+                .into_iter()
+                .filter_map(cli::redact_proxy)
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+    }
+    copts.set_proxy(proxy);
+    copts.set_tls(tls);
+    if let Some(l) = &login {
+        info!("logging in via form at: {}", l.url());
+    }
+    copts.set_login(login);
+    if auth.is_some() {
+        info!(
+            "authenticating requests to: {}",
+            seeds
+                .iter()
+                .filter(|s| s.scheme() != "file")
+                .map(|s| s.origin().ascii_serialization())
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+    }
+    copts.set_auth(auth);
+    copts.set_normalizer(UrlNormalizer::new(
+        args.strip_params.clone(),
+        !args.keep_query_order,
+        args.fold_trailing_slash,
+    ));
+    // read before writing, in case both are the same file
+    if let Some(file) = &args.target.warc_input {
+        match WarcArchive::open(file, &copts.normalizer()) {
+            Ok(a) => {
+                info!("crawling {} archived response(s) from: {}", a.len(), file);
+                copts.set_archive(Some(a));
+            }
+            Err(e) => {
+                error!("failed reading warc file {}: {}", file, e);
+                exit(1);
+            }
+        }
+    }
+    if let Some(file) = &args.target.har_input {
+        match Har::open(file) {
+            Ok(h) => copts.set_har(Some(h)),
+            Err(e) => {
+                error!("failed reading har file {}: {}", file, e);
+                exit(1);
+            }
+        }
+    }
+    if let Some(file) = &args.warc_output {
+        let append = args.target.resume || args.target.resume_strict;
+        match WarcWriter::create(file, append, &url) {
+            Ok(w) => {
+                info!("writing fetched responses to: {}", file);
+                copts.set_warc_writer(Some(w));
+            }
+            Err(e) => {
+                error!("failed creating warc file {}: {}", file, e);
+                exit(1);
+            }
+        }
+    }
+    if let Some(rps) = args.host_req_per_sec {
+        copts.set_host_requests_per_second(rps);
+    }
+    if let Some(lc) = args.host_limit_concurrent {
+        copts.set_host_limit_concurrent(lc);
+    }
+    if args.ignore_robots {
+        warn!("ignoring robots.txt rules");
+    }
+    let eopts = cli::build_extract_options(&args);
 
     let urldb: UrlDb = UrlDb::new();
     let mut uc = urldb.clone();
     cli::fill_urldb_from_state(&mut uc, &mut in_state); // resume
+    if args.refresh {
+        info!("refreshing; revisiting previously visited urls");
+        uc.unvisit_visited_urls();
+        in_state.depth_reached = 0;
+    }
+
+    let cookiejar: CookieJar = CookieJar::new();
+    let mut cj = cookiejar.clone();
+    cli::fill_cookiejar_from_state(&mut cj, &mut in_state); // resume
+    if let Some(file) = &args.cookies {
+        match cli::fill_cookiejar_from_file(&mut cj, file) {
+            Ok(n) => info!("imported {} cookie(s) from: {}", n, file),
+            Err(e) => {
+                error!("failed importing cookies from {}: {}", file, e);
+                exit(1);
+            }
+        }
+    }
+    copts.set_cookie_jar(cj);
 
     let worddb: WordDb = WordDb::new();
     let mut wc = worddb.clone();
@@ -120,45 +288,75 @@ async fn main() -> Result<(), Error> {
         depth_reached, len_words, len_urls
     );
 
-    if args.no_write {
-        info!("Skipping dictionary creation");
-    } else {
-        let mut file =
-            fs::File::create(args.output.clone()).expect("Error creating dictionary file");
-        let mut contents = String::new();
-        worddb.iter().for_each(|word| {
-            let line = format!("{}\n", word);
-            contents.push_str(&line);
-        });
-        file.write_all(contents.as_bytes())
-            .expect("Error writing to dictionary");
-        info!("dictionary written to: {}", args.output);
-    }
+    write_dictionary(&worddb, &args);
 
     if args.output_state {
         let out_state = State {
             starting_url: url,
+            seeds,
             depth_reached,
             visited: urldb.visited_urls_iter().collect(),
             staged: urldb.staged_urls_iter().collect(),
             unvisited: urldb.unvisited_urls_iter().collect(),
             skipped: urldb.skipped_urls_iter().collect(),
             errored: urldb.errored_urls_iter().collect(),
+            oversized: urldb.oversized_urls_iter().collect(),
+            status_codes: urldb.status_codes_iter().collect(),
+            validators: urldb.validators_iter().collect(),
+            requeues: urldb.requeues_iter().collect(),
+            // session and login cookies are credentials; only saved when asked
+            cookies: match args.state_cookies {
+                true => cookiejar.netscape_lines(),
+                false => Vec::new(),
+            },
+            warc_input: args.target.warc_input,
+            har_input: args.target.har_input,
             depth: args.depth,
             filters: args.filters,
             include_css: args.include_css,
             include_js: args.include_js,
             req_per_sec: args.req_per_sec,
             limit_concurrent: args.limit_concurrent,
+            host_req_per_sec: args.host_req_per_sec,
+            host_limit_concurrent: args.host_limit_concurrent,
+            max_retries: args.max_retries,
+            retry_backoff: args.retry_backoff,
+            max_retry_delay: args.max_retry_delay,
+            connect_timeout: args.connect_timeout,
+            timeout: args.timeout,
+            max_redirects: args.max_redirects,
+            accept_status: args.accept_status,
+            max_doc_size: args.max_doc_size,
+            truncate_oversized: args.truncate_oversized,
+            strip_params: args.strip_params,
+            keep_query_order: args.keep_query_order,
+            fold_trailing_slash: args.fold_trailing_slash,
             min_word_length: args.min_word_length,
             max_word_length: args.max_word_length,
             site_policy: args.site_policy,
             user_agent: args.user_agent,
-            headers: args.header,
+            headers: cli::redact_headers(&args.header),
+            follow_links: args.follow_links,
+            follow_rel: args.follow_rel,
+            include: args.include,
+            exclude: args.exclude,
+            include_glob: args.include_glob,
+            exclude_glob: args.exclude_glob,
+            proxy: cli::redact_proxy(&args.proxy),
+            http_proxy: cli::redact_proxy(&args.http_proxy),
+            https_proxy: cli::redact_proxy(&args.https_proxy),
+            no_proxy: args.no_proxy,
+            ca_cert: args.ca_cert,
+            client_cert: args.client_cert,
+            client_key: args.client_key,
+            min_tls_version: args.min_tls_version,
+            insecure: args.insecure,
+            ignore_robots: args.ignore_robots,
+            sitemaps: args.sitemaps,
         };
         let url_file = args.state_file;
         if let Ok(j) = serde_json::to_string_pretty(&out_state) {
-            let mut file = fs::File::create(url_file.clone()).expect("Error creating state file");
+            let mut file = create_state_file(&url_file).expect("Error creating state file");
             file.write_all(j.as_bytes())
                 .expect("Error writing state to file");
             info!("state written to file: {}", url_file);
@@ -170,6 +368,65 @@ async fn main() -> Result<(), Error> {
     Ok(())
 }
 
+/// Extract words from the document read from stdin, without crawling, and
+/// write the dictionary.
+fn extract_stdin(args: &Cli) {
+    let doc_res = cli::read_stdin_document(args);
+    if let Err(e) = doc_res {
+        error!("{}", e);
+        exit(1);
+    }
+    let doc = doc_res.unwrap();
+    if args.output_state {
+        warn!("no state to write when extracting from stdin");
+    }
+
+    let worddb: WordDb = WordDb::new();
+    let mut wc = worddb.clone();
+    if args.append {
+        cli::fill_worddb_from_file(&mut wc, &args.output);
+    }
+    Extractor::new(cli::build_extract_options(args), wc).words_from_doc(&doc);
+    info!("unique words {}", worddb.len());
+
+    write_dictionary(&worddb, args);
+}
+
+/// Write the words to the dictionary file, unless asked not to.
+fn write_dictionary(worddb: &WordDb, args: &Cli) {
+    if args.no_write {
+        info!("Skipping dictionary creation");
+        return;
+    }
+    let mut file = fs::File::create(args.output.clone()).expect("Error creating dictionary file");
+    let mut contents = String::new();
+    worddb.iter().for_each(|word| {
+        let line = format!("{}\n", word);
+        contents.push_str(&line);
+    });
+    file.write_all(contents.as_bytes())
+        .expect("Error writing to dictionary");
+    info!("dictionary written to: {}", args.output);
+}
+
+/// Creates the state file, or truncates an existing one, readable and
+/// writable only by its owner.
+fn create_state_file(path: &str) -> std::io::Result<fs::File> {
+    let mut opts = fs::OpenOptions::new();
+    opts.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        opts.mode(0o600);
+        let file = opts.open(path)?;
+        // the mode only applies to newly created files
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        Ok(file)
+    }
+    #[cfg(not(unix))]
+    opts.open(path)
+}
+
 fn setup_logger(m: MultiProgress, f: &LevelFilter) -> () {
     let filter_str = format!("none,wdict={}", f.as_str());
     let logenv = env_logger::Env::default().default_filter_or(filter_str);